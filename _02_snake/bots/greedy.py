@@ -0,0 +1,92 @@
+#!/usr/bin/env python3
+# An external bot to start from: heads for the food, avoiding the cells
+# taken by snakes and those around the hazards.
+#
+#   cargo run --bin sim -- --external "python3 bots/greedy.py"
+#   cargo run -- --bot-cmd "python3 bots/greedy.py" --bot-log bot.log
+#
+# It gets a line of JSON at a time on its standard input: a welcome, then
+# a snapshot of the game every tick, and answers each snapshot with a
+# line on its standard output, the name of a move. Whatever it prints on
+# its standard error goes to the log.
+
+import json
+import sys
+
+STEPS = {"up": (0, -1), "down": (0, 1), "left": (-1, 0), "right": (1, 0)}
+# on hexagons every odd row is half a cell to the right, so going up or
+# down a row the column changes with the row the snake is on
+HEX_STEPS = [
+    {"left": (-1, 0), "right": (1, 0), "up-left": (-1, -1), "up-right": (0, -1),
+     "down-left": (-1, 1), "down-right": (0, 1)},
+    {"left": (-1, 0), "right": (1, 0), "up-left": (0, -1), "up-right": (1, -1),
+     "down-left": (0, 1), "down-right": (1, 1)},
+]
+# what the left/right and the top/bottom edges of the named worlds do;
+# the others are named after their edges, "wall/twist" and the like
+SHAPES = {
+    "walls": ("wall", "wall"),
+    "torus": ("wrap", "wrap"),
+    "cylinder": ("wrap", "wall"),
+    "mobius": ("twist", "wall"),
+    "klein": ("twist", "wrap"),
+    "projective": ("twist", "twist"),
+}
+
+
+def main():
+    me = 0
+    for line in sys.stdin:
+        message = json.loads(line)
+        if message["type"] == "welcome":
+            me = message["player"]
+            continue
+
+        width, height = message["board"]
+        world = message["world"]
+        edge_x, edge_y = SHAPES.get(world) or world.split("/")
+        hex_grid = message.get("grid", "square") == "hex"
+        taken = {tuple(cell) for snake in message["snakes"] for cell in snake["body"]}
+        # the hazards, and wherever they might step next, are no place to go
+        hazards = [tuple(h["pos"]) for h in message.get("hazards", [])]
+        mine = next(s for s in message["snakes"] if s["player"] == me)
+        head = tuple(mine["body"][0])
+        food = tuple(message["food"])
+
+        steps = HEX_STEPS[head[1] % 2] if hex_grid else STEPS
+
+        def step(name):
+            x, y = head[0] + steps[name][0], head[1] + steps[name][1]
+            if not 0 <= x < width:
+                if edge_x == "wall":
+                    return None
+                x %= width
+                if edge_x == "twist":
+                    y = height - 1 - y
+            if not 0 <= y < height:
+                if edge_y == "wall":
+                    return None
+                y %= height
+                if edge_y == "twist":
+                    x = width - 1 - x
+            return x, y
+
+        def distance(cell, to=food):
+            if hex_grid:
+                # in axial coordinates, the column less the half rows
+                q, r = cell[0] - cell[1] // 2, cell[1]
+                tq, tr = to[0] - to[1] // 2, to[1]
+                return (abs(q - tq) + abs(r - tr) + abs(q - tq + r - tr)) // 2
+            return abs(cell[0] - to[0]) + abs(cell[1] - to[1])
+
+        def near_hazard(cell):
+            return any(distance(cell, h) <= 1 for h in hazards)
+
+        free = [name for name in steps
+                if step(name) and step(name) not in taken and not near_hazard(step(name))]
+        move = min(free, key=lambda name: distance(step(name)), default="nop")
+        print(move, flush=True)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,388 @@
+// Reference bots playing through the Controller interface
+//
+// Each bot drives the snake of the player it was made for; once that
+// snake is out, it has nothing more to say.
+
+use std::collections::VecDeque;
+
+use utypes::Pos;
+use control::Controller;
+use game::Command;
+use game::Dir;
+use game::Game;
+use game::Snake;
+
+// Cells the head of `snake` must not enter on the next tick: the snakes
+// on the board, but for its own tail as it moves away (unless the snake
+// eats, but then it's the food cell). The tails of the others may stay,
+// their snakes may eat. Next to a hazard is no place to be either, it
+// may step there.
+#[derive(Clone)]
+struct Obstacles {
+    width: i16,
+    cells: Vec<bool>,
+}
+
+impl Obstacles {
+    fn new(game: &Game, snake: &Snake) -> Obstacles {
+        let mut obstacles = Obstacles {
+            width: game.board().x,
+            cells: vec![false; game.board().x as usize * game.board().y as usize],
+        };
+
+        for other in game.snakes() {
+            let len = if other.player == snake.player { other.len() - 1 } else { other.len() };
+            for p in other.into_iter().take(len) {
+                obstacles.set(p);
+            }
+        }
+        for hazard in game.hazards() {
+            obstacles.set(hazard.pos);
+            for &dir in game.tiling().dirs() {
+                if let Some(p) = game.next_pos(hazard.pos, dir) {
+                    obstacles.set(p);
+                }
+            }
+        }
+
+        obstacles
+    }
+
+    fn index(&self, pos: Pos) -> usize {
+        pos.y as usize * self.width as usize + pos.x as usize
+    }
+
+    fn contains(&self, pos: Pos) -> bool {
+        self.cells[self.index(pos)]
+    }
+
+    fn set(&mut self, pos: Pos) {
+        let idx = self.index(pos);
+        self.cells[idx] = true;
+    }
+}
+
+// moves that don't kill the snake right away
+fn safe_moves(game: &Game, snake: &Snake, obstacles: &Obstacles) -> Vec<(Dir, Pos)> {
+    let head = snake.head();
+    let back = snake.dir.opposite();
+
+    game.tiling().dirs().iter()
+        .filter(|&&dir| dir != back)
+        .filter_map(|&dir| game.next_pos(head, dir).map(|pos| (dir, pos)))
+        .filter(|&(_, pos)| !obstacles.contains(pos))
+        .collect()
+}
+
+// number of cells reachable from `from` (itself included)
+fn reachable_area(game: &Game, obstacles: &Obstacles, from: Pos) -> usize {
+    let mut visited = obstacles.clone();
+    let mut queue = VecDeque::new();
+    let mut area = 0;
+
+    visited.set(from);
+    queue.push_back(from);
+
+    while let Some(pos) = queue.pop_front() {
+        area += 1;
+        for &dir in game.tiling().dirs() {
+            if let Some(next) = game.next_pos(pos, dir) {
+                if !visited.contains(next) {
+                    visited.set(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    area
+}
+
+// first move of the shortest path from the head to the food
+fn path_to_food(game: &Game, snake: &Snake, obstacles: &Obstacles) -> Option<Dir> {
+    let mut visited = obstacles.clone();
+    let mut queue = VecDeque::new();
+
+    for (dir, pos) in safe_moves(game, snake, obstacles) {
+        visited.set(pos);
+        queue.push_back((dir, pos));
+    }
+
+    while let Some((first, pos)) = queue.pop_front() {
+        if pos == game.food().pos {
+            return Some(first);
+        }
+        for &dir in game.tiling().dirs() {
+            if let Some(next) = game.next_pos(pos, dir) {
+                if !visited.contains(next) {
+                    visited.set(next);
+                    queue.push_back((first, next));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Heads straight for the food, only avoiding immediate death.
+pub struct Greedy {
+    player: usize,
+}
+
+impl Greedy {
+    /// The bot for the snake of `player`.
+    pub fn new(player: usize) -> Greedy {
+        Greedy { player }
+    }
+}
+
+impl Controller for Greedy {
+    fn command(&mut self, game: &Game) -> Command {
+        let snake = match game.player_snake(self.player) {
+            Some(snake) => snake,
+            None => return Command::Nop,
+        };
+        let obstacles = Obstacles::new(game, snake);
+
+        safe_moves(game, snake, &obstacles).into_iter()
+            .min_by_key(|&(_, pos)| game.distance(pos, game.food().pos))
+            .map_or(Command::Nop, |(dir, _)| Command::Move(dir))
+    }
+}
+
+/// Follows the shortest path to the food as long as it doesn't lead
+/// into a pocket too small for the snake; otherwise goes where there
+/// is the most room.
+pub struct Pathfinder {
+    player: usize,
+}
+
+impl Pathfinder {
+    /// The bot for the snake of `player`.
+    pub fn new(player: usize) -> Pathfinder {
+        Pathfinder { player }
+    }
+}
+
+impl Controller for Pathfinder {
+    fn command(&mut self, game: &Game) -> Command {
+        let snake = match game.player_snake(self.player) {
+            Some(snake) => snake,
+            None => return Command::Nop,
+        };
+        let obstacles = Obstacles::new(game, snake);
+        let len = snake.len();
+
+        let area_after = |pos: Pos| {
+            let mut obstacles = obstacles.clone();
+            obstacles.set(pos);
+            1 + game.tiling().dirs().iter()
+                .filter_map(|&dir| game.next_pos(pos, dir))
+                .filter(|&next| !obstacles.contains(next))
+                .map(|next| reachable_area(game, &obstacles, next))
+                .max()
+                .unwrap_or(0)
+        };
+
+        if let Some(dir) = path_to_food(game, snake, &obstacles) {
+            let pos = game.next_pos(snake.head(), dir).unwrap();
+            if area_after(pos) >= len {
+                return Command::Move(dir);
+            }
+        }
+
+        safe_moves(game, snake, &obstacles).into_iter()
+            .max_by_key(|&(_, pos)| (area_after(pos), -game.distance(pos, game.food().pos)))
+            .map_or(Command::Nop, |(dir, _)| Command::Move(dir))
+    }
+}
+
+/// Walks a Hamiltonian cycle over the whole board, which is slow but
+/// never fails to fill it. A cycle only exists if one of the board sides
+/// is even; on odd by odd boards the bot falls back to the `Pathfinder`.
+pub struct Hamiltonian {
+    player: usize,
+    cycle: Vec<Pos>,    // cells in the cycle order
+    order: Vec<usize>,  // cell -> its place in the cycle
+    forward: bool,
+}
+
+impl Hamiltonian {
+    /// The bot for the snake of `player`.
+    pub fn new(player: usize) -> Hamiltonian {
+        Hamiltonian { player, cycle: vec![], order: vec![], forward: true }
+    }
+
+    // Snakes the rows leaving the first column out, then comes back up
+    // along the first column. Needs an even number of rows, so the board
+    // is walked transposed if only the number of columns is even.
+    fn build_cycle(&mut self, game: &Game, snake: &Snake) {
+        let board = game.board();
+        let transpose = board.y % 2 != 0;
+        let (w, h) = if transpose { (board.y, board.x) } else { (board.x, board.y) };
+
+        let mut cycle = Vec::with_capacity(w as usize * h as usize);
+        for y in 0 .. h {
+            if y % 2 == 0 {
+                cycle.extend((1 .. w).map(|x| Pos{x, y}));
+            } else {
+                cycle.extend((1 .. w).rev().map(|x| Pos{x, y}));
+            }
+        }
+        cycle.extend((0 .. h).rev().map(|y| Pos{x: 0, y}));
+
+        if transpose {
+            for p in cycle.iter_mut() {
+                *p = Pos{x: p.y, y: p.x};
+            }
+        }
+
+        self.order = vec![0; cycle.len()];
+        for (i, p) in cycle.iter().enumerate() {
+            self.order[(p.y * board.x + p.x) as usize] = i;
+        }
+        self.cycle = cycle;
+
+        // go the way the snake is already heading, not back into its neck
+        let head = snake.head();
+        let neck = snake.into_iter().nth(1);
+        self.forward = true;
+        self.forward = neck != Some(self.next_cell(game, head));
+    }
+
+    fn next_cell(&self, game: &Game, pos: Pos) -> Pos {
+        let n = self.cycle.len();
+        let i = self.order[(pos.y * game.board().x + pos.x) as usize];
+        if self.forward {
+            self.cycle[(i + 1) % n]
+        } else {
+            self.cycle[(i + n - 1) % n]
+        }
+    }
+}
+
+impl Controller for Hamiltonian {
+    fn command(&mut self, game: &Game) -> Command {
+        if game.board().x % 2 != 0 && game.board().y % 2 != 0 {
+            return Pathfinder::new(self.player).command(game);
+        }
+
+        let snake = match game.player_snake(self.player) {
+            Some(snake) => snake,
+            None => return Command::Nop,
+        };
+        if self.cycle.is_empty() {
+            self.build_cycle(game, snake);
+        }
+
+        let head = snake.head();
+        let next = self.next_cell(game, head);
+
+        // on hexagons too: the cells above and below are neighbors
+        game.tiling().dirs().iter()
+            .find(|&&dir| game.next_pos(head, dir) == Some(next))
+            .map_or(Command::Nop, |&dir| Command::Move(dir))
+    }
+}
+
+pub const NAMES: [&str; 3] = ["greedy", "pathfinder", "hamiltonian"];
+
+/// The bot of that name, for the snake of `player`.
+pub fn by_name(name: &str, player: usize) -> Option<Box<dyn Controller>> {
+    match name {
+        "greedy" => Some(Box::new(Greedy::new(player))),
+        "pathfinder" => Some(Box::new(Pathfinder::new(player))),
+        "hamiltonian" => Some(Box::new(Hamiltonian::new(player))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn play_out(game: &mut Game, bot: &mut dyn Controller, max_ticks: usize) {
+    for _ in 0 .. max_ticks {
+        let cmd = bot.command(game);
+        if game.exec(cmd).is_none() {
+            break;
+        }
+    }
+}
+
+#[test]
+fn bots_simple_test() {
+    use utypes::Board;
+    use topology::NAMED;
+    use tiling::Tiling;
+
+    for &(_, topology) in NAMED.iter() {
+        for &tiling in [Tiling::Square, Tiling::Hex].iter() {
+            // on hexagons, the twisted worlds take an odd number of rows
+            let boards = [Board{x: 8, y: 8}, Board{x: 8, y: 9}];
+            let board = match boards.iter().find(|&&b| topology.fits(b, tiling)) {
+                Some(&board) => board,
+                None => continue,
+            };
+            let mut game = Game::new(board, 4, topology).with_tiling(tiling);
+            play_out(&mut game, &mut Greedy::new(0), 1000);
+            assert!(game.snake().len() > 4);
+
+            let mut game = Game::new(board, 4, topology).with_tiling(tiling);
+            play_out(&mut game, &mut Pathfinder::new(0), 1000);
+            assert!(game.snake().len() > 8);
+        }
+    }
+}
+
+// The snake of player 1 heads right, straight for the food, but the one
+// of player 0 lies across the way: the bots go round it.
+#[test]
+fn bots_other_snake_test() {
+    use utypes::Board;
+    use topology::Topology;
+    use tiling::Tiling;
+    use game::{Color, SnakeState, Snapshot};
+
+    let snapshot = Snapshot {
+        board: Board{x: 10, y: 10},
+        topology: Topology::WALLS,
+        tiling: Tiling::Square,
+        snakes: vec![
+            SnakeState {
+                player: 0, color: Color::White, dir: Dir::Up,
+                body: (3 .. 8).map(|y| Pos{x: 4, y}).collect(),
+            },
+            SnakeState {
+                player: 1, color: Color::Blue, dir: Dir::Right,
+                body: vec![Pos{x: 3, y: 5}, Pos{x: 2, y: 5}, Pos{x: 1, y: 5}],
+            },
+        ],
+        food: Pos{x: 8, y: 5},
+        hazards: vec![],
+        outs: vec![None, None],
+    };
+
+    let bots: [Box<dyn Controller>; 2] = [Box::new(Greedy::new(1)), Box::new(Pathfinder::new(1))];
+    for mut bot in bots {
+        let mut game = Game::from_snapshot(&snapshot).unwrap();
+        let cmd = bot.command(&game);
+        assert!(cmd == Command::Move(Dir::Up) || cmd == Command::Move(Dir::Down), "{:?}", cmd);
+        game.exec_all(&[Command::Nop, cmd]);
+        assert_eq!(None, game.out(1));
+    }
+}
+
+#[test]
+fn hamiltonian_fills_board_test() {
+    use utypes::Board;
+    use topology::Topology;
+    use tiling::Tiling;
+
+    for &board in [Board{x: 6, y: 6}, Board{x: 7, y: 6}, Board{x: 6, y: 7}].iter() {
+        for i in 0 .. 10 {
+            let tiling = if i % 2 == 0 { Tiling::Square } else { Tiling::Hex };
+            let mut game = Game::new(board, 4, Topology::WALLS).with_tiling(tiling);
+            play_out(&mut game, &mut Hamiltonian::new(0), 100_000);
+            assert_eq!(game.snake().len(), (board.x * board.y) as usize);
+        }
+    }
+}
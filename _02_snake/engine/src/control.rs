@@ -0,0 +1,44 @@
+// Snake controllers: whoever decides where the snake goes next
+
+use std::collections::VecDeque;
+
+use game::Command;
+use game::Game;
+
+/// Something that drives a snake: a human at the keyboard or a bot.
+///
+/// The controller is asked once per tick and only gets a read-only view
+/// of the game, so it can't cheat by poking at the state directly.
+pub trait Controller {
+    fn command(&mut self, game: &Game) -> Command;
+}
+
+/// Plays back a fixed list of commands, then exits. Handy for tests.
+pub struct Script {
+    commands: VecDeque<Command>,
+}
+
+impl Script {
+    pub fn new(commands: &[Command]) -> Script {
+        Script { commands: commands.iter().cloned().collect() }
+    }
+}
+
+impl Controller for Script {
+    fn command(&mut self, _game: &Game) -> Command {
+        self.commands.pop_front().unwrap_or(Command::Exit)
+    }
+}
+
+#[test]
+fn script_simple_test() {
+    use utypes::Board;
+    use topology::Topology;
+    use game::Dir;
+
+    let game = Game::new(Board{x: 16, y: 16}, 4, Topology::TORUS);
+    let mut script = Script::new(&[Command::Nop, Command::Move(Dir::Up)]);
+    assert_eq!(Command::Nop, script.command(&game));
+    assert_eq!(Command::Move(Dir::Up), script.command(&game));
+    assert_eq!(Command::Exit, script.command(&game));
+}
@@ -0,0 +1,323 @@
+// Gym-style environment: the game as a reset/step loop for learning agents
+
+#[cfg(test)]
+use control::Controller;
+use utypes::Pos;
+use utypes::Board;
+use topology::{Edge, Topology};
+use game::Command;
+use game::Game;
+use game::GameOver;
+
+/// Turns the game state into the flat vector of numbers an agent sees.
+///
+/// `encode` is called on every step, so it must write into `out` without
+/// allocating; any scratch space is set up once in `init`.
+pub trait Encoder {
+    fn size(&self, board: Board) -> usize;
+    fn init(&mut self, _board: Board) {}
+    fn encode(&mut self, game: &Game, out: &mut [f32]);
+}
+
+/// The whole board as four channels: snake body, snake head, food and
+/// walls (the hazards among them), each `(board.y + 2) x (board.x + 2)`
+/// row-major, the extra ring being the border (walls there only on the
+/// edges that are walls).
+pub struct Grid;
+
+impl Encoder for Grid {
+    fn size(&self, board: Board) -> usize {
+        4 * (board.x as usize + 2) * (board.y as usize + 2)
+    }
+
+    fn encode(&mut self, game: &Game, out: &mut [f32]) {
+        let w = game.board().x as usize + 2;
+        let h = game.board().y as usize + 2;
+        let plane = w * h;
+        let cell = |p: Pos| (p.y as usize + 1) * w + (p.x as usize + 1);
+
+        for v in out.iter_mut() {
+            *v = 0.0;
+        }
+
+        for p in game.snake().into_iter().skip(1) {
+            out[cell(p)] = 1.0;
+        }
+        out[plane + cell(game.snake().head())] = 1.0;
+        out[2 * plane + cell(game.food().pos)] = 1.0;
+
+        let walls = &mut out[3 * plane .. 4 * plane];
+        for hazard in game.hazards() {
+            walls[cell(hazard.pos)] = 1.0;
+        }
+        if !game.topology().y.is_open() {
+            for x in 0 .. w {
+                walls[x] = 1.0;
+                walls[(h - 1) * w + x] = 1.0;
+            }
+        }
+        if !game.topology().x.is_open() {
+            for y in 0 .. h {
+                walls[y * w] = 1.0;
+                walls[y * w + w - 1] = 1.0;
+            }
+        }
+    }
+}
+
+/// What the snake sees looking around from its head: eight rays, starting
+/// straight ahead and going clockwise, each giving the inverse distance to
+/// the nearest wall, body segment (or hazard) and food (0 if none on the
+/// ray).
+pub struct Rays {
+    occupied: Vec<bool>,
+}
+
+impl Rays {
+    pub fn new() -> Rays {
+        Rays { occupied: vec![] }
+    }
+}
+
+impl Default for Rays {
+    fn default() -> Rays {
+        Rays::new()
+    }
+}
+
+impl Encoder for Rays {
+    fn size(&self, _board: Board) -> usize {
+        3 * 8
+    }
+
+    fn init(&mut self, board: Board) {
+        self.occupied = vec![false; board.x as usize * board.y as usize];
+    }
+
+    fn encode(&mut self, game: &Game, out: &mut [f32]) {
+        let width = game.board().x as usize;
+        let index = |p: Pos| p.y as usize * width + p.x as usize;
+
+        for v in self.occupied.iter_mut() {
+            *v = false;
+        }
+        for p in game.snake().into_iter().skip(1) {
+            self.occupied[index(p)] = true;
+        }
+        for hazard in game.hazards() {
+            self.occupied[index(hazard.pos)] = true;
+        }
+
+        let ahead = game.snake().dir.into_pos();
+        let right = Pos{x: -ahead.y, y: ahead.x};
+        let rays = [
+            ahead, ahead + right, right, right - ahead,
+            Pos{x: 0, y: 0} - ahead, Pos{x: 0, y: 0} - ahead - right,
+            Pos{x: 0, y: 0} - right, ahead - right,
+        ];
+
+        let board = game.board();
+        let topology = game.topology();
+        let max_steps = board.x.max(board.y);
+
+        for (i, &step) in rays.iter().enumerate() {
+            let (mut wall, mut body, mut food) = (0.0, 0.0, 0.0);
+            let mut pos = game.snake().head();
+            let mut step = step;
+
+            for d in 1 ..= max_steps {
+                let next = pos + step;
+                pos = match game.wrap(next) {
+                    Some(p) => p,
+                    None => {
+                        wall = 1.0 / d as f32;
+                        break;
+                    }
+                };
+                // through a twisted edge the ray comes back mirrored
+                if topology.x == Edge::Twist && (next.x < 0 || next.x >= board.x) {
+                    step.y = -step.y;
+                }
+                if topology.y == Edge::Twist && (next.y < 0 || next.y >= board.y) {
+                    step.x = -step.x;
+                }
+                if body == 0.0 && self.occupied[index(pos)] {
+                    body = 1.0 / d as f32;
+                }
+                if food == 0.0 && pos == game.food().pos {
+                    food = 1.0 / d as f32;
+                }
+            }
+
+            out[3 * i] = wall;
+            out[3 * i + 1] = body;
+            out[3 * i + 2] = food;
+        }
+    }
+}
+
+/// Reward shaping. `approach` is paid per cell the head gets closer to the
+/// food (and charged when it moves away), which helps early training.
+#[derive(Debug, Copy, Clone)]
+pub struct Rewards {
+    pub food: f32,
+    pub death: f32,
+    pub win: f32,
+    pub step: f32,
+    pub approach: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards { food: 1.0, death: -1.0, win: 10.0, step: -0.01, approach: 0.0 }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Info {
+    pub ticks: u64,
+    pub length: usize,
+    pub over: Option<GameOver>,
+    pub truncated: bool,    // stopped for starving, not by the game rules
+}
+
+pub struct Env<E: Encoder> {
+    board: Board,
+    snake_len: u16,
+    topology: Topology,
+    game: Game,
+    encoder: E,
+    rewards: Rewards,
+    observation: Vec<f32>,
+    ticks: u64,
+    hungry_ticks: u64,
+    max_hungry_ticks: u64,
+    truncated: bool,        // starved, so done until the next reset
+}
+
+impl<E: Encoder> Env<E> {
+    pub fn new(board: Board, snake_len: u16, topology: Topology,
+               mut encoder: E, rewards: Rewards) -> Env<E> {
+        encoder.init(board);
+        let size = encoder.size(board);
+
+        Env {
+            board,
+            snake_len,
+            topology,
+            game: Game::with_seed(board, snake_len, topology, 0),
+            encoder,
+            rewards,
+            observation: vec![0.0; size],
+            ticks: 0,
+            hungry_ticks: 0,
+            // enough to get anywhere on the board a couple of times over
+            max_hungry_ticks: 2 * board.x as u64 * board.y as u64,
+            truncated: false,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn reset(&mut self, seed: u64) -> &[f32] {
+        self.game = Game::with_seed(self.board, self.snake_len, self.topology, seed);
+        self.ticks = 0;
+        self.hungry_ticks = 0;
+        self.truncated = false;
+        self.encoder.encode(&self.game, &mut self.observation);
+        &self.observation
+    }
+
+    /// Plays one tick. Once done, by the rules or for starving, steps
+    /// change nothing until the next `reset`.
+    pub fn step(&mut self, action: Command) -> (&[f32], f32, bool, Info) {
+        if self.game.over().is_some() || self.truncated {
+            return (&self.observation, 0.0, true, self.info());
+        }
+
+        let len = self.game.snake().len();
+        let dist = self.game.distance(self.game.snake().head(), self.game.food().pos);
+
+        let mut reward = 0.0;
+
+        if self.game.exec(action).is_some() {
+            self.ticks += 1;
+            self.hungry_ticks += 1;
+            reward += self.rewards.step;
+
+            if self.game.snake().len() > len {
+                self.hungry_ticks = 0;
+                reward += self.rewards.food;
+            } else {
+                let new_dist = self.game.distance(self.game.snake().head(), self.game.food().pos);
+                reward += self.rewards.approach * (dist - new_dist) as f32;
+            }
+
+            self.truncated = self.hungry_ticks >= self.max_hungry_ticks;
+            self.encoder.encode(&self.game, &mut self.observation);
+        } else {
+            reward += match self.game.over() {
+                Some(GameOver::BoardFull) => self.rewards.food + self.rewards.win,
+                Some(GameOver::HitWall) | Some(GameOver::HitSelf) | Some(GameOver::HitSnake)
+                | Some(GameOver::HitHazard) => self.rewards.death,
+                _ => 0.0,
+            };
+        }
+
+        let info = self.info();
+        let done = info.over.is_some() || info.truncated;
+
+        (&self.observation, reward, done, info)
+    }
+
+    fn info(&self) -> Info {
+        Info {
+            ticks: self.ticks,
+            length: self.game.snake().len(),
+            over: self.game.over(),
+            truncated: self.truncated,
+        }
+    }
+}
+
+#[test]
+fn env_simple_test() {
+    use bots;
+
+    let board = Board{x: 8, y: 8};
+    let mut env = Env::new(board, 4, Topology::WALLS, Grid, Rewards::default());
+    assert_eq!(env.reset(7).len(), 4 * 10 * 10);
+    assert_eq!(env.reset(7).iter().sum::<f32>(), 5.0 + 36.0); // snake, food, walls
+
+    // a greedy bot eats at least once and then dies or starves
+    let mut bot = bots::Greedy::new(0);
+    let mut total = 0.0;
+    let ticks = loop {
+        let cmd = bot.command(env.game());
+        let (_, reward, done, info) = env.step(cmd);
+        total += reward;
+        if done {
+            assert!(info.length > 4);
+            break info.ticks;
+        }
+    };
+    assert!(total > 0.0);
+
+    // done is done, starved or not
+    let (_, reward, done, info) = env.step(Command::Nop);
+    assert_eq!((reward, done, info.ticks), (0.0, true, ticks));
+}
+
+#[test]
+fn rays_simple_test() {
+    let board = Board{x: 8, y: 8};
+    let mut env = Env::new(board, 4, Topology::WALLS, Rays::new(), Rewards::default());
+    let obs = env.reset(3).to_vec();
+    let head = env.game().snake().head();
+
+    // heading right: the wall straight ahead, the own body right behind
+    assert_eq!(obs[0], 1.0 / (board.x - head.x) as f32);
+    assert_eq!(obs[4 * 3 + 1], 1.0);
+}
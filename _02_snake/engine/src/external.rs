@@ -0,0 +1,273 @@
+// External bots: programs in any language, playing through their
+// standard input and output
+//
+// The bot is spoken to in the JSON messages of `wire`, a line each:
+// first a welcome telling it which snake is its own and how long it may
+// think about a move (`tick_ms`), then a snapshot of the game every tick.
+// It answers each snapshot with a line of its own: a command message or
+// just the name of the command (`up`, `nop`, ...). Only the first line
+// after a snapshot counts, any more are dropped before the next snapshot
+// goes out rather than taken for the answer to it. A bot that takes too
+// long, answers anything else or goes away is disqualified, its snake
+// exits from then on. The first move may take a while longer, for the bot
+// to start up.
+
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command as Process, Stdio};
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use control::Controller;
+use game::{Command, Game};
+use wire::{command_by_name, Message, Welcome, MAX_FRAME, VERSION};
+
+/// How long a bot may think about a move unless told otherwise.
+pub const DEFAULT_LIMIT: Duration = Duration::from_millis(100);
+
+// on top of the limit for the first move
+const STARTUP: Duration = Duration::from_secs(1);
+
+// what the bot says: on its standard output (the moves) or its standard
+// error (anything it wants logged)
+enum Line {
+    Out(String),
+    Err(String),
+}
+
+// passes on the lines of `input` until it ends
+fn pass_lines<R: Read>(input: R, tx: mpsc::Sender<Line>, line: fn(String) -> Line) {
+    let mut input = BufReader::new(input);
+    loop {
+        let mut text = String::new();
+        // no move is that long, but a stream of junk could be
+        match input.by_ref().take(MAX_FRAME as u64).read_line(&mut text) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                let text = text.trim_end().to_string();
+                if tx.send(line(text)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// A bot running as a process of its own, see above for how it plays.
+pub struct External {
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<Line>,
+    player: usize,
+    limit: Duration,
+    welcomed: bool,
+    log: Option<Box<dyn Write>>,
+    disqualified: Option<String>,
+}
+
+impl External {
+    /// Starts `command` as the bot playing the snake of `player`, giving
+    /// it `limit` for every move. The command is the program and its
+    /// arguments, quoted as in the shell (`sh -c`, so a path with spaces
+    /// goes in quotes). The shell then gives way to the program, which is
+    /// what gets stopped once the bot is done; a program that isn't there
+    /// is a bot gone by its first move.
+    pub fn spawn(command: &str, player: usize, limit: Duration) -> io::Result<External> {
+        if command.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no bot to start"));
+        }
+        let mut child = Process::new("sh")
+            .arg("-c")
+            .arg(format!("exec {}", command))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (tx, lines) = mpsc::channel();
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let err_tx = tx.clone();
+        thread::spawn(move || pass_lines(stdout, tx, Line::Out));
+        thread::spawn(move || pass_lines(stderr, err_tx, Line::Err));
+
+        Ok(External {
+            stdin: child.stdin.take().unwrap(),
+            child,
+            lines,
+            player,
+            limit,
+            welcomed: false,
+            log: None,
+            disqualified: None,
+        })
+    }
+
+    /// Writes down all that is said to the bot (`> `), by it (`< `, or
+    /// `- ` if dropped) and on its standard error (`# `), and why it was
+    /// disqualified (`! `), a line each. Without a log, the bot's standard
+    /// error is dropped.
+    pub fn log_to(&mut self, log: Box<dyn Write>) {
+        self.log = Some(log);
+    }
+
+    /// Why the bot was disqualified, if it was.
+    pub fn disqualified(&self) -> Option<&str> {
+        self.disqualified.as_deref()
+    }
+
+    fn note(&mut self, mark: char, text: &str) {
+        let written = self.log.as_mut().map(|log| writeln!(log, "{} {}", mark, text));
+        if let Some(Err(_)) = written {
+            self.log = None;
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let line = message.json(VERSION);
+        self.note('>', &line);
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    // drops what the bot said past its last answer, noting it down
+    fn drain(&mut self) {
+        while let Ok(line) = self.lines.try_recv() {
+            match line {
+                Line::Out(text) => self.note('-', &text),
+                Line::Err(text) => self.note('#', &text),
+            }
+        }
+    }
+
+    // the next line the bot answers with, noting what it says on the side
+    fn answer(&mut self, limit: Duration) -> Result<String, String> {
+        let deadline = Instant::now() + limit;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(Line::Out(text)) => {
+                    self.note('<', &text);
+                    return Ok(text);
+                }
+                Ok(Line::Err(text)) => self.note('#', &text),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!("no move within {} ms", limit.as_millis()));
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err("the bot is gone".to_string()),
+            }
+        }
+    }
+
+    fn disqualify(&mut self, why: String) -> Command {
+        self.note('!', &why);
+        if let Some(log) = self.log.as_mut() {
+            let _ = log.flush();
+        }
+        let _ = self.child.kill();
+        self.disqualified = Some(why);
+        Command::Exit
+    }
+}
+
+impl Controller for External {
+    fn command(&mut self, game: &Game) -> Command {
+        if self.disqualified.is_some() {
+            return Command::Exit;
+        }
+
+        let snapshot = game.snapshot();
+        let mut limit = self.limit;
+        if !self.welcomed {
+            self.welcomed = true;
+            limit += STARTUP;
+            let welcome = Welcome {
+                version: VERSION,
+                player: Some(self.player),
+                players: game.players(),
+                board: snapshot.board,
+                topology: snapshot.topology,
+                tiling: snapshot.tiling,
+                tick_ms: self.limit.as_millis() as u32,
+            };
+            if let Err(e) = self.send(&Message::Welcome(welcome)) {
+                return self.disqualify(format!("could not write to the bot: {}", e));
+            }
+        }
+        self.drain();
+        if let Err(e) = self.send(&Message::Snapshot(snapshot)) {
+            return self.disqualify(format!("could not write to the bot: {}", e));
+        }
+
+        let line = match self.answer(limit) {
+            Ok(line) => line,
+            Err(why) => return self.disqualify(why),
+        };
+        let cmd = match Message::parse_json(&line) {
+            Some(Message::Command(cmd)) => Some(cmd),
+            Some(_) => None,
+            None => command_by_name(line.trim()),
+        };
+        match cmd {
+            Some(cmd) => cmd,
+            None => self.disqualify(format!("not a move: {}", line)),
+        }
+    }
+}
+
+impl Drop for External {
+    fn drop(&mut self) {
+        if let Some(log) = self.log.as_mut() {
+            let _ = log.flush();
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn external_simple_test() {
+    use std::fs;
+    use std::env;
+    use utypes::Board;
+    use topology::Topology;
+    use game::Dir;
+
+    let game = Game::new(Board{x: 16, y: 16}, 4, Topology::TORUS);
+    // a space in the path, quoted
+    let script = env::temp_dir().join(format!("external simple test {}.sh", std::process::id()));
+    let play = |lines: &str, limit| {
+        fs::write(&script, lines).unwrap();
+        External::spawn(&format!("sh '{}'", script.display()), 0, limit).unwrap()
+    };
+
+    // the welcome and the snapshot, then a snapshot a move
+    let mut bot = play("read w; read s; echo up\n\
+                        read s; echo '{\"type\":\"command\",\"command\":\"left\"}'\n\
+                        read s; echo sideways\n", DEFAULT_LIMIT * 10);
+    assert_eq!(Command::Move(Dir::Up), bot.command(&game));
+    assert_eq!(Command::Move(Dir::Left), bot.command(&game));
+    assert_eq!(Command::Exit, bot.command(&game));
+    assert_eq!(Some("not a move: sideways"), bot.disqualified());
+    assert_eq!(Command::Exit, bot.command(&game));
+
+    // a second line for the same snapshot answers nothing
+    let mut bot = play("read w; read s; echo up; echo down\n\
+                        read s; echo left\n", DEFAULT_LIMIT * 10);
+    assert_eq!(Command::Move(Dir::Up), bot.command(&game));
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(Command::Move(Dir::Left), bot.command(&game));
+    assert_eq!(None, bot.disqualified());
+
+    let mut bot = play("read w; read s; echo up; sleep 5\n", Duration::from_millis(50));
+    assert_eq!(Command::Move(Dir::Up), bot.command(&game));
+    assert_eq!(Command::Exit, bot.command(&game));
+    assert_eq!(Some("no move within 50 ms"), bot.disqualified());
+
+    let mut bot = play("exit\n", DEFAULT_LIMIT * 10);
+    assert_eq!(Command::Exit, bot.command(&game));
+    assert!(bot.disqualified().is_some());
+    fs::remove_file(&script).unwrap();
+}
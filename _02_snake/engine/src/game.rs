@@ -0,0 +1,1060 @@
+// Game state and others
+
+extern crate rand;
+
+use std::cmp;
+use std::mem;
+
+use self::rand::{Rng, SeedableRng};
+use self::rand::rngs::StdRng;
+
+use utypes::Pos;
+use utypes::Board;
+use topology::Topology;
+use tiling::Tiling;
+
+/// Direction the snake can head in. Which of them there are depends on
+/// the `Tiling`: the first four on squares, left, right and the
+/// diagonals on hexagons.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Dir {
+    Down,
+    Left,
+    Right,
+    Up,
+    DownLeft,
+    DownRight,
+    UpLeft,
+    UpRight,
+}
+
+/// What the snake is told to do on a tick.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Command {
+    /// Turn to `Dir` (turning back is ignored) and move on.
+    Move(Dir),
+    /// Keep going in the current direction.
+    Nop,
+    /// Quit the game.
+    Exit,
+}
+
+/// Color of a game object, the renderer decides what it really looks like.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Color {
+    White,
+    Blue,
+    Green,
+    Red,
+}
+
+/// The thing the snake is after.
+#[derive(Clone)]
+pub struct Food {
+    pub pos: Pos,
+}
+
+/// Something moving about the board on its own, out of the snake's way
+/// but for its head: a snake running into one, or caught by one, is out.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Hazard {
+    pub pos: Pos,
+    pub motion: Motion,
+}
+
+/// How a hazard moves, a step a tick at most.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Motion {
+    /// On in `Dir` until something is in the way, then back the other
+    /// way: back and forth along a row, a column or a diagonal.
+    Patrol(Dir),
+    /// After the nearest snake head, resting now and then.
+    Chase,
+}
+
+/// The snake, its body stored as a ring so moving doesn't shift anything.
+#[derive(Debug, Clone)]
+pub struct Snake {
+    body: Vec<Pos>,
+    head_idx: usize,
+    pub dir: Dir,
+    pub color: Color,
+    /// Whose snake it is, 0 in a game for one.
+    pub player: usize,
+}
+
+/// Why the game ended.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GameOver {
+    /// The player quit.
+    Exit,
+    /// Ran into a wall at the board edge.
+    HitWall,
+    /// Bit its own body.
+    HitSelf,
+    /// Ran into another snake, or head-on into one.
+    HitSnake,
+    /// Ran into a hazard, or got caught by one.
+    HitHazard,
+    /// The snake takes up the whole board: nothing left to eat, a win.
+    BoardFull,
+}
+
+/// The game rules and state. The state can only be changed by `exec`
+/// (or `apply`), so whoever holds a `&Game` gets a consistent read-only
+/// view.
+///
+/// A game can have several players, a snake each. All the snakes move at
+/// once on a tick; a snake that is out leaves the board, unless it was
+/// the last one, and the game is over once they are all out.
+///
+/// A copy of a game is a whole one, the random numbers included: it plays
+/// on just the way the original would.
+#[derive(Clone)]
+pub struct Game {
+    board: Board,
+    snakes: Vec<Snake>,             // the ones still on the board
+    food: Food,
+    hazards: Vec<Hazard>,
+    topology: Topology,
+    tiling: Tiling,
+    seed: u64,
+    outs: Vec<Option<GameOver>>,    // by player, why they are out
+    over: Option<GameOver>,
+    events: Vec<Event>,             // what the last tick did
+    rng: StdRng,
+    // scratch space for a tick, kept so that ticks don't allocate
+    moves: Vec<Result<Pos, GameOver>>,
+    steps: Vec<Result<Pos, GameOver>>,
+    going_out: Vec<(usize, GameOver)>,
+}
+
+/// Something that happened on a tick. Applied in order to another copy
+/// of the game (see `Game::apply`), the events of a tick make the same
+/// changes to it.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Event {
+    /// The head of the snake of `player` moved to `head`, heading `dir`.
+    /// The tail followed unless the snake `grew`.
+    Moved { player: usize, head: Pos, dir: Dir, grew: bool },
+    /// The food is somewhere else now.
+    Food(Pos),
+    /// Hazard number `hazard` moved to `pos` and goes on with `motion`.
+    Hazard { hazard: usize, pos: Pos, motion: Motion },
+    /// The snake of `player` is out of the game.
+    Out { player: usize, reason: GameOver },
+}
+
+/// The state of a game as it is, enough for a copy of it to go on with
+/// `apply` (but not to play by the rules on: the random numbers aren't
+/// in it).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    pub board: Board,
+    pub topology: Topology,
+    pub tiling: Tiling,
+    pub snakes: Vec<SnakeState>,
+    pub food: Pos,
+    pub hazards: Vec<Hazard>,
+    /// By player, why they are out.
+    pub outs: Vec<Option<GameOver>>,
+}
+
+/// A snake in a `Snapshot`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SnakeState {
+    pub player: usize,
+    pub color: Color,
+    pub dir: Dir,
+    /// From the head to the tail.
+    pub body: Vec<Pos>,
+}
+
+/// Where the first snake's head and tail were before a tick, and whether
+/// the food moved. It says nothing of the other snakes or the hazards,
+/// so the renderers here draw the scene anew from the game instead;
+/// `Game::events` has the whole story of a tick.
+pub struct GameUpdate {
+    pub head_prev_pos: Option<Pos>,
+    pub tail_prev_pos: Option<Pos>,
+    pub food_renew: bool,
+}
+
+impl Dir {
+    /// The directions on squares, handy for trying each of them (see
+    /// `Tiling::dirs` for any board).
+    pub fn all() -> [Dir; 4] {
+        [Dir::Down, Dir::Left, Dir::Right, Dir::Up]
+    }
+
+    pub fn opposite(self) -> Dir {
+        match self {
+            Dir::Down => Dir::Up,
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+            Dir::Up => Dir::Down,
+            Dir::DownLeft => Dir::UpRight,
+            Dir::DownRight => Dir::UpLeft,
+            Dir::UpLeft => Dir::DownRight,
+            Dir::UpRight => Dir::DownLeft,
+        }
+    }
+
+    /// The direction a notch to the left on `tiling`: a quarter turn on
+    /// squares, a sixth of a turn on hexagons. A direction the tiling
+    /// doesn't have stays as it is.
+    pub fn turn_left(self, tiling: Tiling) -> Dir {
+        let ring = tiling.ring();
+        self.turn(ring, ring.len() - 1)
+    }
+
+    /// The direction a notch to the right on `tiling`, see `turn_left`.
+    pub fn turn_right(self, tiling: Tiling) -> Dir {
+        self.turn(tiling.ring(), 1)
+    }
+
+    // `notches` further clockwise round `ring`
+    fn turn(self, ring: &[Dir], notches: usize) -> Dir {
+        match ring.iter().position(|&dir| dir == self) {
+            Some(i) => ring[(i + notches) % ring.len()],
+            None => self,
+        }
+    }
+
+    // the step on squares, the diagonals going to the corners
+    pub(crate) fn into_pos(self) -> Pos {
+        match self {
+            Dir::Down => Pos{x: 0, y: 1},
+            Dir::Left => Pos{x: -1, y: 0},
+            Dir::Right => Pos{x: 1, y: 0},
+            Dir::Up => Pos{x: 0, y: -1},
+            Dir::DownLeft => Pos{x: -1, y: 1},
+            Dir::DownRight => Pos{x: 1, y: 1},
+            Dir::UpLeft => Pos{x: -1, y: -1},
+            Dir::UpRight => Pos{x: 1, y: -1},
+        }
+    }
+}
+
+impl Snake {
+    pub(crate) fn with_capacity(size: usize, head: Pos) -> Snake {
+        let mut s = Snake {
+            body: Vec::with_capacity(size),
+            head_idx: 0,
+            dir: Dir::Right,
+            color: Color::Green,
+            player: 0,
+        };
+        s.body.push(head);
+        s
+    }
+
+    pub fn head(&self) -> Pos {
+        self.body[self.head_idx]
+    }
+
+    pub fn tail(&self) -> Pos {
+        self.body[self.tail_idx()]
+    }
+
+    /// Whether the head may move to `pos` without biting the body
+    /// (the tail is fine, it moves away).
+    pub fn can_step(&self, pos: Pos) -> bool {
+        match self.body.iter().position(|ref x| x == &&pos) {
+            None => true,
+            Some(v) => v == self.tail_idx(),
+        }
+    }
+
+    pub(crate) fn step(&mut self, pos: Pos) {
+        let tidx = self.tail_idx();
+        self.body[tidx] = pos;
+        self.head_idx = tidx;
+    }
+
+    pub(crate) fn grow(&mut self, new_head: Pos) {
+        self.body.insert(self.head_idx, new_head);
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.body.contains(&pos)
+    }
+
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.body.is_empty()
+    }
+
+    fn tail_idx(&self) -> usize {
+        (self.body.len() + self.head_idx - 1) % self.body.len()
+    }
+}
+
+// the colors of the players' snakes, in turn
+const SNAKE_COLORS: [Color; 4] = [Color::Green, Color::Blue, Color::Red, Color::White];
+
+// the hazards start at least this many steps from the snake heads
+const HAZARD_ROOM: i16 = 4;
+// a chaser rests on one tick in this many, for the snakes to get away
+const CHASE_REST: u32 = 4;
+
+impl Game {
+    /// A new game with a random seed. Where the snake going off an edge
+    /// ends up is up to the `topology`.
+    pub fn new(board: Board, snake_len: u16, topology: Topology) -> Game {
+        Self::with_seed(board, snake_len, topology, rand::random())
+    }
+
+    /// Same seed, same game (given the same commands).
+    pub fn with_seed(board: Board, snake_len: u16, topology: Topology, seed: u64) -> Game {
+        Self::with_players(board, snake_len, topology, seed, 1)
+    }
+
+    /// A game for several players, the snakes starting on different rows.
+    pub fn with_players(board: Board, snake_len: u16, topology: Topology, seed: u64,
+                        players: usize) -> Game {
+        assert!(board.x > 4 && board.y > 4);
+        assert!(snake_len < i16::MAX as u16);
+        assert!(players > 0 && players <= board.y as usize);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let snake_len = snake_len as i16;
+        let snake_len = cmp::min(snake_len, board.x - 2);
+        let capacity = 2usize * board.x as usize * board.y as usize;
+
+        let mut snakes: Vec<Snake> = Vec::with_capacity(players);
+        while snakes.len() < players {
+            let snake_pos = Pos {
+                x: rng.gen_range(0, (board.x - snake_len) / 2),
+                y: rng.gen_range(0, board.y),
+            };
+            if snakes.iter().any(|s| s.head().y == snake_pos.y) {
+                continue;
+            }
+
+            let mut snake = Snake::with_capacity(capacity, snake_pos);
+            snake.player = snakes.len();
+            snake.color = SNAKE_COLORS[snake.player % SNAKE_COLORS.len()];
+
+            let grow_dir_vec = snake.dir.into_pos();
+            for l in 1 .. snake_len {
+                snake.grow(snake_pos + l * grow_dir_vec)
+            }
+            snakes.push(snake);
+        }
+
+        let mut game = Game {
+            board,
+            snakes,
+            food: Food { pos: Pos{x: 0, y: 0} }, // tentative
+            hazards: Vec::new(),
+            topology,
+            tiling: Tiling::Square,
+            seed,
+            outs: vec![None; players],
+            over: None,
+            events: Vec::new(),
+            rng,
+            moves: Vec::with_capacity(players),
+            steps: Vec::with_capacity(players),
+            going_out: Vec::with_capacity(players),
+        };
+        game.generate_food();
+
+        game
+    }
+
+    /// The same game on another tiling than squares, before its first
+    /// tick. The snakes start along a row, the way they do on squares.
+    /// The world has to fit the tiling (see `Topology::fits`).
+    pub fn with_tiling(mut self, tiling: Tiling) -> Game {
+        assert!(self.topology.fits(self.board, tiling));
+        self.tiling = tiling;
+        self
+    }
+
+    /// The same game with `count` hazards on the board, before its first
+    /// tick (and after `with_tiling`): every other one patrols, the
+    /// others chase. They start where nothing else is, away from the
+    /// snake heads; fewer of them if there is no room.
+    pub fn with_hazards(mut self, count: usize) -> Game {
+        for i in 0 .. count {
+            let motion = if i % 2 == 0 {
+                let dirs = self.tiling.dirs();
+                Motion::Patrol(dirs[self.rng.gen_range(0, dirs.len())])
+            } else {
+                Motion::Chase
+            };
+
+            // a few tries, the board may be crowded
+            for _ in 0 .. 100 {
+                let pos = Pos {
+                    x: self.rng.gen_range(0, self.board.x),
+                    y: self.rng.gen_range(0, self.board.y),
+                };
+                let away = self.snakes.iter().all(|s| self.distance(s.head(), pos) >= HAZARD_ROOM);
+                if away && self.hazard_can_enter(pos) {
+                    self.hazards.push(Hazard { pos, motion });
+                    break;
+                }
+            }
+        }
+        self
+    }
+
+    /// A game going on from `snapshot`, `None` if the snapshot doesn't
+    /// make sense.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Option<Game> {
+        let board = snapshot.board;
+        let players = snapshot.outs.len();
+        if board.x <= 4 || board.y <= 4 || players == 0 || snapshot.snakes.is_empty()
+            || !snapshot.topology.fits(board, snapshot.tiling) {
+            return None;
+        }
+        let area = board.x as usize * board.y as usize;
+
+        let inside = |p: Pos| p.x >= 0 && p.y >= 0 && p.x < board.x && p.y < board.y;
+        let all_out = snapshot.outs.iter().all(Option::is_some);
+        let mut snakes: Vec<Snake> = Vec::with_capacity(snapshot.snakes.len());
+        for state in &snapshot.snakes {
+            let player = state.player;
+            if player >= players || snakes.iter().any(|s| s.player == player)
+                || (snapshot.outs[player].is_some() && !all_out)
+                || state.body.is_empty() || state.body.len() > area
+                || !state.body.iter().all(|&p| inside(p)) {
+                return None;
+            }
+            snakes.push(Snake {
+                body: state.body.clone(),
+                head_idx: 0,
+                dir: state.dir,
+                color: state.color,
+                player,
+            });
+        }
+        if !inside(snapshot.food) || !snapshot.hazards.iter().all(|h| inside(h.pos))
+            || snapshot.hazards.len() > area {
+            return None;
+        }
+
+        let over = if all_out {
+            snakes.iter().map(|s| snapshot.outs[s.player]).next().unwrap_or(None)
+        } else {
+            None
+        };
+
+        Some(Game {
+            board,
+            snakes,
+            food: Food { pos: snapshot.food },
+            hazards: snapshot.hazards.clone(),
+            topology: snapshot.topology,
+            tiling: snapshot.tiling,
+            seed: 0,
+            outs: snapshot.outs.clone(),
+            over,
+            events: Vec::new(),
+            rng: StdRng::seed_from_u64(0),
+            moves: Vec::with_capacity(players),
+            steps: Vec::with_capacity(players),
+            going_out: Vec::with_capacity(players),
+        })
+    }
+
+    // The game a snapshot was taken of, given its seed and the random
+    // numbers it had yet to draw, to play by the rules on again.
+    pub(crate) fn resume(snapshot: &Snapshot, seed: u64, rng: StdRng) -> Option<Game> {
+        Self::from_snapshot(snapshot).map(|game| Game { seed, rng, ..game })
+    }
+
+    // the random numbers yet to be drawn
+    pub(crate) fn rng(&self) -> &StdRng {
+        &self.rng
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board,
+            topology: self.topology,
+            tiling: self.tiling,
+            snakes: self.snakes.iter().map(|s| SnakeState {
+                player: s.player,
+                color: s.color,
+                dir: s.dir,
+                body: s.into_iter().collect(),
+            }).collect(),
+            food: self.food.pos,
+            hazards: self.hazards.clone(),
+            outs: self.outs.clone(),
+        }
+    }
+
+    // returns false if there is no room left for the food
+    fn generate_food(&mut self) -> bool {
+        let taken: usize = self.snakes.iter().map(Snake::len).sum::<usize>() + self.hazards.len();
+        let free_cells = (self.board.x as usize * self.board.y as usize).saturating_sub(taken);
+        if free_cells == 0 {
+            return false;
+        }
+
+        loop {
+            let pos = Pos {
+                x: self.rng.gen_range(0, self.board.x),
+                y: self.rng.gen_range(0, self.board.y),
+            };
+
+            let taken = self.snakes.iter().any(|s| s.contains(pos))
+                || self.hazards.iter().any(|h| h.pos == pos);
+            if !taken {
+                self.food = Food{ pos };
+                return true;
+            }
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        self.board
+    }
+
+    /// The snake of a game for one; in a game of several, the first one
+    /// still on the board.
+    pub fn snake(&self) -> &Snake {
+        &self.snakes[0]
+    }
+
+    /// The snakes on the board, in the order of their players.
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    /// The snake of `player`, `None` once it left the board.
+    pub fn player_snake(&self, player: usize) -> Option<&Snake> {
+        self.snakes.iter().find(|s| s.player == player)
+    }
+
+    pub fn players(&self) -> usize {
+        self.outs.len()
+    }
+
+    /// Why `player` is out, `None` while they are still in.
+    pub fn out(&self, player: usize) -> Option<GameOver> {
+        self.outs.get(player).cloned().unwrap_or(None)
+    }
+
+    pub fn food(&self) -> &Food {
+        &self.food
+    }
+
+    pub fn hazards(&self) -> &[Hazard] {
+        &self.hazards
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn tiling(&self) -> Tiling {
+        self.tiling
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Why the game is over, `None` while it is still on. With several
+    /// players, why the last of them went out.
+    pub fn over(&self) -> Option<GameOver> {
+        self.over
+    }
+
+    /// What happened on the last tick.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Plays one tick. Returns what changed, or `None` once the game is
+    /// over, the reason then being in `over()`.
+    pub fn exec(&mut self, cmd: Command) -> Option<GameUpdate> {
+        self.exec_all(&[cmd])
+    }
+
+    /// Plays one tick of a game of several, `commands` by player (the
+    /// missing ones being `Nop`).
+    pub fn exec_all(&mut self, commands: &[Command]) -> Option<GameUpdate> {
+        if self.over.is_some() {
+            return None;
+        }
+        self.events.clear();
+
+        // where each snake is heading, or why it can't go on
+        let tiling = self.tiling;
+        let mut moves = mem::take(&mut self.moves);
+        moves.clear();
+        for snake in &mut self.snakes {
+            let dir = match commands.get(snake.player).cloned().unwrap_or(Command::Nop) {
+                Command::Move(dir) => dir,
+                Command::Nop => snake.dir,
+                Command::Exit => {
+                    moves.push(Err(GameOver::Exit));
+                    continue;
+                }
+            };
+            snake.dir = Self::normalize_dir(tiling, snake, dir);
+            moves.push(Ok(snake.head()));
+        }
+        for (m, snake) in moves.iter_mut().zip(&self.snakes) {
+            if let Ok(head) = *m {
+                *m = self.next_pos(head, snake.dir).ok_or(GameOver::HitWall);
+            }
+        }
+
+        let food = self.food.pos;
+        let mut results = mem::take(&mut self.steps);
+        results.clear();
+        results.extend((0 .. moves.len())
+            .map(|i| moves[i].and_then(|pos| self.check_step(i, pos, &moves))));
+
+        let update = GameUpdate {
+            head_prev_pos: Some(self.snakes[0].head()),
+            tail_prev_pos: Some(self.snakes[0].tail()),
+            food_renew: false,
+        };
+        let update = match results[0] {
+            Ok(pos) if pos == food => GameUpdate { tail_prev_pos: None, food_renew: true, ..update },
+            Ok(_) => update,
+            Err(_) => GameUpdate { head_prev_pos: None, tail_prev_pos: None, ..update },
+        };
+
+        let mut outs = mem::take(&mut self.going_out);
+        outs.clear();
+        let mut fed = false;
+        for (i, &result) in results.iter().enumerate() {
+            let player = self.snakes[i].player;
+            match result {
+                Ok(head) => {
+                    let grew = head == food;
+                    fed |= grew;
+                    self.record(Event::Moved { player, head, dir: self.snakes[i].dir, grew });
+                }
+                Err(reason) => outs.push((player, reason)),
+            }
+        }
+
+        if fed {
+            if self.generate_food() {
+                let pos = self.food.pos;
+                self.events.push(Event::Food(pos));
+            } else {
+                for snake in &self.snakes {
+                    if !outs.iter().any(|&(p, _)| p == snake.player) {
+                        outs.push((snake.player, GameOver::BoardFull));
+                    }
+                }
+            }
+        }
+
+        self.move_hazards(&mut outs);
+        self.take_out(&outs);
+        self.moves = moves;
+        self.steps = results;
+        self.going_out = outs;
+
+        match self.over {
+            Some(_) => None,
+            None => Some(update),
+        }
+    }
+
+    /// Brings this copy of a game along with the original, given the
+    /// events of one of its ticks (no rules played, no random numbers
+    /// drawn). Events that don't fit the game are ignored.
+    pub fn apply(&mut self, events: &[Event]) -> Option<GameUpdate> {
+        if self.over.is_some() {
+            return None;
+        }
+        self.events.clear();
+
+        let mut outs = Vec::new();
+        for &event in events {
+            match event {
+                Event::Moved { player, head, .. } => {
+                    if self.player_snake(player).is_some() && self.wrap(head) == Some(head) {
+                        self.record(event);
+                    }
+                }
+                Event::Food(pos) => {
+                    if self.wrap(pos) == Some(pos) {
+                        self.record(event);
+                    }
+                }
+                Event::Hazard { hazard, pos, .. } => {
+                    if hazard < self.hazards.len() && self.wrap(pos) == Some(pos) {
+                        self.record(event);
+                    }
+                }
+                Event::Out { player, reason } => {
+                    if self.out(player).is_none() && player < self.players() {
+                        outs.push((player, reason));
+                    }
+                }
+            }
+        }
+        self.take_out(&outs);
+
+        match self.over {
+            Some(_) => None,
+            None => Some(GameUpdate { head_prev_pos: None, tail_prev_pos: None, food_renew: false }),
+        }
+    }
+
+    // makes the change of a move or of the food, and keeps the event
+    fn record(&mut self, event: Event) {
+        match event {
+            Event::Moved { player, head, dir, grew } => {
+                let snake = self.snakes.iter_mut().find(|s| s.player == player).unwrap();
+                snake.dir = dir;
+                if grew { snake.grow(head) } else { snake.step(head) }
+            }
+            Event::Food(pos) => self.food = Food { pos },
+            Event::Hazard { hazard, pos, motion } => self.hazards[hazard] = Hazard { pos, motion },
+            Event::Out { .. } => (),
+        }
+        self.events.push(event);
+    }
+
+    // The hazards move one after another, once the snakes did, a chaser
+    // after where the snakes are now. A snake whose head they land on is
+    // out.
+    fn move_hazards(&mut self, outs: &mut Vec<(usize, GameOver)>) {
+        for i in 0 .. self.hazards.len() {
+            let hazard = self.hazards[i];
+            let moved = match hazard.motion {
+                Motion::Patrol(dir) => self.patrol(hazard.pos, dir),
+                Motion::Chase => self.chase(hazard.pos),
+            };
+            if moved == hazard {
+                continue;
+            }
+
+            self.record(Event::Hazard { hazard: i, pos: moved.pos, motion: moved.motion });
+            for snake in &self.snakes {
+                if snake.head() == moved.pos && !outs.iter().any(|&(p, _)| p == snake.player) {
+                    outs.push((snake.player, GameOver::HitHazard));
+                }
+            }
+        }
+    }
+
+    // on, or back the other way if something is in the way
+    fn patrol(&self, pos: Pos, dir: Dir) -> Hazard {
+        let step = |dir| self.next_pos(pos, dir).filter(|&p| self.hazard_can_enter(p));
+        match step(dir) {
+            Some(pos) => Hazard { pos, motion: Motion::Patrol(dir) },
+            None => {
+                let back = dir.opposite();
+                Hazard { pos: step(back).unwrap_or(pos), motion: Motion::Patrol(back) }
+            }
+        }
+    }
+
+    // a step closer to the nearest snake head, any of the closest ones
+    fn chase(&mut self, pos: Pos) -> Hazard {
+        let stay = Hazard { pos, motion: Motion::Chase };
+        if self.rng.gen_range(0, CHASE_REST) == 0 {
+            return stay;
+        }
+        let heads = self.snakes.iter().map(Snake::head);
+        let target = match heads.min_by_key(|&h| self.distance(pos, h)) {
+            Some(target) => target,
+            None => return stay,
+        };
+
+        let closest = match self.hazard_steps(pos).map(|p| self.distance(p, target)).min() {
+            Some(d) => d,
+            None => return stay,
+        };
+        let count = self.hazard_steps(pos).filter(|&p| self.distance(p, target) == closest).count();
+        let nth = self.rng.gen_range(0, count);
+        let pos = self.hazard_steps(pos).filter(|&p| self.distance(p, target) == closest).nth(nth);
+        Hazard { pos: pos.unwrap(), motion: Motion::Chase }
+    }
+
+    // where a hazard may step from `pos`, in the order of the tiling's
+    // directions
+    fn hazard_steps(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        self.tiling.dirs().iter()
+            .filter_map(move |&dir| self.next_pos(pos, dir))
+            .filter(move |&p| self.hazard_can_enter(p))
+    }
+
+    // whether a hazard may go to `pos`: nothing there, or a snake head
+    fn hazard_can_enter(&self, pos: Pos) -> bool {
+        pos != self.food.pos
+            && !self.hazards.iter().any(|h| h.pos == pos)
+            && !self.snakes.iter().any(|s| s.contains(pos) && s.head() != pos)
+    }
+
+    // the players going out on this tick: off the board with them, but
+    // for the last ones, they stay as the game ends
+    fn take_out(&mut self, outs: &[(usize, GameOver)]) {
+        for &(player, reason) in outs {
+            self.outs[player] = Some(reason);
+            self.events.push(Event::Out { player, reason });
+        }
+
+        if self.outs.iter().all(Option::is_some) {
+            self.over = outs.first().map(|&(_, reason)| reason);
+        } else {
+            let outs_now = &self.outs;
+            self.snakes.retain(|s| outs_now[s.player].is_none());
+        }
+    }
+
+    // turning back, or where the tiling has no way to go, is ignored
+    fn normalize_dir(tiling: Tiling, snake: &Snake, dir: Dir) -> Dir {
+        if dir == snake.dir.opposite() || !tiling.dirs().contains(&dir) {
+            snake.dir
+        } else {
+            dir
+        }
+    }
+
+    // Whether snake `i` may step to `pos`, with `moves` where all of
+    // them are heading. The tails move away unless their snake eats;
+    // two heads meeting are both out.
+    fn check_step(&self, i: usize, pos: Pos, moves: &[Result<Pos, GameOver>]) -> Result<Pos, GameOver> {
+        let food = self.food.pos;
+        if self.hazards.iter().any(|h| h.pos == pos) {
+            return Err(GameOver::HitHazard);
+        }
+
+        for (j, snake) in self.snakes.iter().enumerate() {
+            if j == i {
+                if pos != food && !snake.can_step(pos) {
+                    return Err(GameOver::HitSelf);
+                }
+                continue;
+            }
+
+            if moves[j] == Ok(pos) {
+                return Err(GameOver::HitSnake);
+            }
+            let tail_moves = moves[j].is_ok() && moves[j] != Ok(food);
+            if snake.contains(pos) && !(tail_moves && pos == snake.tail()) {
+                return Err(GameOver::HitSnake);
+            }
+        }
+
+        Ok(pos)
+    }
+
+    /// Where a step in `dir` from `pos` leads, `None` if into the wall
+    /// or if the tiling has no way to go in `dir`.
+    pub fn next_pos(&self, pos: Pos, dir: Dir) -> Option<Pos> {
+        self.tiling.step(pos, dir).and_then(|pos| self.wrap(pos))
+    }
+
+    /// Brings a position that went one cell off the board back to it,
+    /// `None` if there is a wall instead.
+    pub fn wrap(&self, pos: Pos) -> Option<Pos> {
+        self.topology.wrap(self.board, pos)
+    }
+
+    /// Number of steps between two cells, shortcuts through the edges included.
+    pub fn distance(&self, a: Pos, b: Pos) -> i16 {
+        self.topology.distance(self.board, self.tiling, a, b)
+    }
+}
+
+impl<'a> IntoIterator for &'a Snake {
+    type Item = Pos;
+    type IntoIter = SnakeIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SnakeIterator { snake: self, index: 0 }
+    }
+}
+
+/// Goes over the snake from the head to the tail.
+pub struct SnakeIterator<'a> {
+    snake: &'a Snake,
+    index: usize,
+}
+
+impl<'a> Iterator for SnakeIterator<'a> {
+    type Item = Pos;
+
+    fn next(&mut self) -> Option<Pos> {
+        if self.index == self.snake.body.len() {
+            return None;
+        }
+
+        let idx = (self.snake.head_idx + self.index) % self.snake.body.len();
+        self.index += 1;
+
+        Some(self.snake.body[idx])
+    }
+}
+
+#[test]
+fn snake_simple_test() {
+    let mut snake = Snake::with_capacity(40, Pos{x: 1, y: 1});
+    assert_eq!(snake.head(), Pos{x: 1, y: 1});
+    snake.step(Pos{x: 1, y: 2});
+    assert_eq!(snake.head(), Pos{x: 1, y: 2});
+    snake.grow(Pos{x: 2, y: 2});
+    assert_eq!(snake.head(), Pos{x: 2, y: 2});
+    assert_eq!(snake.tail(), Pos{x: 1, y: 2});
+    assert_eq!(snake.body.len(), 2);
+    snake.grow(Pos{x: 2, y: 3});
+    snake.grow(Pos{x: 1, y: 3});
+    assert!(snake.can_step(Pos{x: 1, y: 2}));
+    assert!(!snake.can_step(Pos{x: 2, y: 3}));
+    assert!(snake.contains(Pos{x: 2, y: 3}));
+    assert!(!snake.contains(Pos{x: 1, y: 1}));
+
+    let mut iter = IntoIterator::into_iter(&snake);
+    assert_eq!(Pos{x: 1, y: 3}, iter.next().unwrap());
+    assert_eq!(Pos{x: 2, y: 3}, iter.next().unwrap());
+    assert_eq!(Pos{x: 2, y: 2}, iter.next().unwrap());
+    assert_eq!(Pos{x: 1, y: 2}, iter.next().unwrap());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn game_seed_test() {
+    let mut a = Game::with_seed(Board{x: 8, y: 8}, 4, Topology::WALLS, 42);
+    let mut b = Game::with_seed(Board{x: 8, y: 8}, 4, Topology::WALLS, 42);
+    assert_eq!(a.snake().head(), b.snake().head());
+    assert_eq!(a.food.pos, b.food.pos);
+
+    while a.exec(Command::Nop).is_some() {
+        assert!(b.exec(Command::Nop).is_some());
+        assert_eq!(a.food.pos, b.food.pos);
+    }
+    assert_eq!(Some(GameOver::HitWall), a.over);
+    assert!(a.exec(Command::Nop).is_none());
+
+    let mut c = Game::with_seed(Board{x: 8, y: 8}, 4, Topology::TORUS, 42);
+    assert!(c.exec(Command::Exit).is_none());
+    assert_eq!(Some(GameOver::Exit), c.over);
+}
+
+#[test]
+fn game_players_test() {
+    let board = Board{x: 12, y: 10};
+    let mut game = Game::with_players(board, 4, Topology::TORUS, 3, 3);
+    let mut mirror = Game::with_players(board, 4, Topology::TORUS, 3, 3);
+    assert_eq!(3, game.snakes().len());
+    assert_eq!(Color::Blue, game.snakes()[1].color);
+
+    let rows: Vec<i16> = game.snakes().iter().map(|s| s.head().y).collect();
+    assert!(rows[0] != rows[1] && rows[1] != rows[2] && rows[0] != rows[2]);
+
+    let same = |a: &Game, b: &Game| {
+        let cells = |g: &Game| g.snakes().iter()
+            .map(|s| (s.player, s.into_iter().collect::<Vec<_>>())).collect::<Vec<_>>();
+        cells(a) == cells(b) && a.food().pos == b.food().pos
+    };
+
+    // the second player quits, the others go on without their snake
+    assert!(game.exec_all(&[Command::Nop, Command::Exit]).is_some());
+    assert_eq!(Some(GameOver::Exit), game.out(1));
+    assert!(game.player_snake(1).is_none());
+    assert_eq!(vec![0, 2], game.snakes().iter().map(|s| s.player).collect::<Vec<_>>());
+    assert!(game.events().contains(&Event::Out { player: 1, reason: GameOver::Exit }));
+    let events = game.events().to_vec();
+    assert!(mirror.apply(&events).is_some());
+    assert!(same(&game, &mirror));
+
+    // a copy can start from a snapshot too
+    let mut mirror = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert!(same(&game, &mirror));
+    assert_eq!(game.snapshot(), mirror.snapshot());
+
+    for _ in 0 .. 5 {
+        // on rows of their own, they never meet
+        assert!(game.exec_all(&[]).is_some());
+        let events = game.events().to_vec();
+        mirror.apply(&events);
+        assert!(same(&game, &mirror));
+    }
+
+    // the last ones out stay on the board
+    assert!(game.exec_all(&[Command::Exit, Command::Nop, Command::Exit]).is_none());
+    assert_eq!(Some(GameOver::Exit), game.over());
+    assert_eq!(2, game.snakes().len());
+    let events = game.events().to_vec();
+    assert!(mirror.apply(&events).is_none());
+    assert_eq!(Some(GameOver::Exit), mirror.over());
+}
+
+#[test]
+fn game_hex_test() {
+    let mut game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::TORUS, 8).with_tiling(Tiling::Hex);
+    let head = game.snake().head();
+
+    // no way straight up on hexagons, nor back the way it came
+    game.exec(Command::Move(Dir::Up));
+    game.exec(Command::Move(Dir::Left));
+    assert_eq!(Dir::Right, game.snake().dir);
+    assert_eq!(head + Pos{x: 2, y: 0}, game.snake().head());
+
+    game.exec(Command::Move(Dir::UpRight));
+    game.exec(Command::Move(Dir::DownRight));
+    assert_eq!(Dir::DownRight, game.snake().dir);
+    assert_eq!(head + Pos{x: 3, y: 0}, game.snake().head());
+    assert_eq!(Tiling::Hex, Game::from_snapshot(&game.snapshot()).unwrap().tiling());
+}
+
+#[test]
+fn dir_turn_test() {
+    assert_eq!(Dir::Up, Dir::Right.turn_left(Tiling::Square));
+    assert_eq!(Dir::Left, Dir::Up.turn_left(Tiling::Square));
+    assert_eq!(Dir::Down, Dir::Right.turn_right(Tiling::Square));
+    assert_eq!(Dir::UpRight, Dir::Right.turn_left(Tiling::Hex));
+    assert_eq!(Dir::UpRight, Dir::UpLeft.turn_right(Tiling::Hex));
+
+    // all the way round and back where it started
+    for &tiling in [Tiling::Square, Tiling::Hex].iter() {
+        for &dir in tiling.dirs() {
+            let round = tiling.dirs().iter().fold(dir, |d, _| d.turn_right(tiling));
+            assert_eq!(dir, round);
+            assert_eq!(dir, dir.turn_left(tiling).turn_right(tiling));
+        }
+    }
+    assert_eq!(Dir::Up, Dir::Up.turn_left(Tiling::Hex));
+}
+
+#[test]
+fn game_hazard_test() {
+    let mut game = Game::with_seed(Board{x: 16, y: 12}, 4, Topology::TORUS, 3).with_hazards(4);
+    assert_eq!(4, game.hazards().len());
+    assert_eq!(2, game.hazards().iter().filter(|h| h.motion == Motion::Chase).count());
+
+    // the copy moves them along from the events alone
+    let mut mirror = Game::from_snapshot(&game.snapshot()).unwrap();
+    while game.exec(Command::Nop).is_some() {
+        let events = game.events().to_vec();
+        mirror.apply(&events);
+        assert_eq!(game.hazards(), mirror.hazards());
+        assert!(game.hazards().iter()
+                .all(|h| h.pos != game.food().pos && !game.snake().contains(h.pos)));
+    }
+    assert_eq!(Some(GameOver::HitHazard), game.over());
+
+    // one coming the other way gets the snake on the cell between them
+    let mut snapshot = Game::with_seed(Board{x: 16, y: 12}, 4, Topology::TORUS, 3).snapshot();
+    let head = snapshot.snakes[0].body[0];
+    let ahead = Pos{x: (head.x + 2) % 16, y: head.y};
+    snapshot.food = Pos{x: ahead.x, y: (head.y + 6) % 12};
+    snapshot.hazards = vec![Hazard { pos: ahead, motion: Motion::Patrol(Dir::Left) }];
+    let mut game = Game::from_snapshot(&snapshot).unwrap();
+    assert!(game.exec(Command::Nop).is_none());
+    assert_eq!(Some(GameOver::HitHazard), game.over());
+    assert_eq!(game.snake().head(), game.hazards()[0].pos);
+}
@@ -0,0 +1,105 @@
+// History: the last few states of a game, to go back to
+
+extern crate rand;
+
+use std::collections::VecDeque;
+
+use self::rand::rngs::StdRng;
+
+use game::{Game, Snapshot};
+
+/// The states a game went through on its last ticks, the oldest ones
+/// forgotten once there are `capacity` of them. Going back to one of them
+/// is as if the ticks after it never happened: the random numbers are
+/// taken back too.
+pub struct History {
+    states: VecDeque<State>,
+    capacity: usize,
+}
+
+// a game as it was, the random numbers it had yet to draw included
+struct State {
+    snapshot: Snapshot,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History { states: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Keeps the game as it is, before a tick is played on it. What is
+    /// kept is a snapshot of it, the snakes and the hazards, along with
+    /// its random numbers: none of the game's scratch space, nor the
+    /// events of its last tick.
+    pub fn push(&mut self, game: &Game) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        let state = State { snapshot: game.snapshot(), seed: game.seed(), rng: game.rng().clone() };
+        self.states.push_back(state);
+    }
+
+    /// The game as it was `ticks` ticks ago (at least one), or as long ago
+    /// as is kept; `None` if nothing is. The states after it are forgotten.
+    pub fn rewind(&mut self, ticks: usize) -> Option<Game> {
+        let keep = self.states.len().saturating_sub(ticks.max(1));
+        self.states.truncate(keep + 1);
+        self.states.pop_back()
+            .and_then(|state| Game::resume(&state.snapshot, state.seed, state.rng))
+    }
+
+    /// How many ticks there are to go back.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[test]
+fn history_simple_test() {
+    use utypes::Board;
+    use topology::Topology;
+    use game::{Command, Dir};
+
+    let commands = [Command::Move(Dir::Down), Command::Nop, Command::Move(Dir::Left),
+                    Command::Nop, Command::Move(Dir::Up)];
+    let mut game = Game::with_seed(Board{x: 8, y: 8}, 4, Topology::TORUS, 3);
+    let mut history = History::new(3);
+    for _ in 0 .. 20 {
+        for &cmd in commands.iter() {
+            history.push(&game);
+            game.exec(cmd);
+        }
+    }
+    assert_eq!(3, history.len());
+
+    // two ticks back, then the same two ticks again: the same game
+    let mut rewound = history.rewind(2).unwrap();
+    assert_eq!(1, history.len());
+    rewound.exec(commands[3]);
+    rewound.exec(commands[4]);
+    assert_eq!(game.snapshot(), rewound.snapshot());
+    for _ in 0 .. 10 {
+        for &cmd in commands.iter() {
+            game.exec(cmd);
+            rewound.exec(cmd);
+        }
+    }
+    assert_eq!(game.snapshot(), rewound.snapshot());
+
+    // no further back than kept
+    assert!(history.rewind(10).is_some());
+    assert!(history.rewind(1).is_none());
+}
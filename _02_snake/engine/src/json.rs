@@ -0,0 +1,258 @@
+// JSON: just enough of it for the debug form of the wire messages
+//
+// Numbers are kept as `i64`, the messages have no others. Objects keep
+// their keys in order.
+
+use std::fmt;
+
+// how deep arrays and objects may go, not to run out of stack on junk
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref fields) => fields.iter().find(|f| f.0 == key).map(|f| &f.1),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self) -> Option<i64> {
+        match *self {
+            Value::Int(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// None unless the whole of `text` is a single value.
+    pub fn parse(text: &str) -> Option<Value> {
+        let mut parser = Parser { chars: text.chars().collect(), at: 0 };
+        let value = parser.value(0)?;
+        parser.space();
+        if parser.at == parser.chars.len() { Some(value) } else { None }
+    }
+}
+
+/// Writes `s` as a JSON string, quotes and escapes included.
+pub fn write_string(f: &mut dyn fmt::Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for ch in s.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+// compact, on a single line
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Str(ref s) => write_string(f, s),
+            Value::Array(ref items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(ref fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.at += 1;
+        Some(ch)
+    }
+
+    fn space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Option<()> {
+        for ch in word.chars() {
+            if self.next()? != ch {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    fn value(&mut self, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        self.space();
+        match self.peek()? {
+            'n' => self.expect("null").map(|_| Value::Null),
+            't' => self.expect("true").map(|_| Value::Bool(true)),
+            'f' => self.expect("false").map(|_| Value::Bool(false)),
+            '"' => self.string().map(Value::Str),
+            '[' => {
+                self.at += 1;
+                let mut items = Vec::new();
+                self.space();
+                if self.peek()? == ']' {
+                    self.at += 1;
+                    return Some(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.space();
+                    match self.next()? {
+                        ',' => continue,
+                        ']' => return Some(Value::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                self.at += 1;
+                let mut fields = Vec::new();
+                self.space();
+                if self.peek()? == '}' {
+                    self.at += 1;
+                    return Some(Value::Object(fields));
+                }
+                loop {
+                    self.space();
+                    let key = self.string()?;
+                    self.space();
+                    if self.next()? != ':' {
+                        return None;
+                    }
+                    fields.push((key, self.value(depth + 1)?));
+                    self.space();
+                    match self.next()? {
+                        ',' => continue,
+                        '}' => return Some(Value::Object(fields)),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => self.number().map(Value::Int),
+        }
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let start = self.at;
+        if self.peek() == Some('-') {
+            self.at += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.at += 1;
+        }
+        let text: String = self.chars[start .. self.at].iter().collect();
+        text.parse().ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.next()? != '"' {
+            return None;
+        }
+
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Some(s),
+                '\\' => s.push(match self.next()? {
+                    '"' => '"',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let hex: String = (0 .. 4).map(|_| self.next()).collect::<Option<_>>()?;
+                        std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                    }
+                    _ => return None,
+                }),
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+#[test]
+fn json_simple_test() {
+    let text = r#"{"a":[1,-2,true,null],"b":"x\"y\n","c":{}}"#;
+    let value = Value::parse(text).unwrap();
+    assert_eq!(Some(-2), value.get("a").and_then(|a| a.array()).and_then(|a| a[1].int()));
+    assert_eq!(Some("x\"y\n"), value.get("b").and_then(Value::str));
+    assert_eq!(text, value.to_string());
+
+    assert_eq!(Some(Value::Str("é".to_string())), Value::parse(" \"\\u00e9\" "));
+    assert_eq!(None, Value::parse("[1,]"));
+    assert_eq!(None, Value::parse("{\"a\" 1}"));
+    assert_eq!(None, Value::parse(&"[".repeat(100)));
+}
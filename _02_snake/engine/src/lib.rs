@@ -0,0 +1,42 @@
+//! Snake game engine: the rules, the state and the players, with no
+//! terminal attached. The terminal game, the simulator and anything else
+//! that wants to play snake links against this crate.
+//!
+//! A game is a `Game` advanced one tick at a time with `Game::exec`,
+//! each tick reporting what changed as a `GameUpdate`. Who decides on the
+//! `Command` for a tick is up to a `Controller`: a human, a bot from
+//! `bots`, or a learning agent through `env`. Showing the game is up to
+//! a `Renderer`. A game kept as a `Recording` can be played again, one
+//! kept in a `History` can be taken back a few ticks. The board is made
+//! of squares or hexagons (its `Tiling`), and what lies past its edges
+//! is up to its `Topology`. There may be `Hazard`s moving about it too.
+//!
+//! A game can also be for several players, each with a snake, played
+//! over the network with the messages of `wire`: the server plays the
+//! game and sends out a `Snapshot` of it and then the `Event`s of each
+//! tick, the clients `apply` them to their copies. A bot can be a
+//! program of its own too, playing `external`ly over the same messages.
+
+pub mod utypes;
+pub mod topology;
+pub mod tiling;
+pub mod game;
+pub mod control;
+pub mod bots;
+pub mod env;
+pub mod render;
+pub mod record;
+pub mod history;
+pub mod json;
+pub mod wire;
+pub mod external;
+
+pub use utypes::{Board, Pos};
+pub use topology::{Edge, Topology};
+pub use tiling::Tiling;
+pub use game::{Color, Command, Dir, Event, Food, Game, GameOver, GameUpdate, Hazard, Motion,
+                Snake, Snapshot};
+pub use control::{Controller, Script};
+pub use render::Renderer;
+pub use record::Recording;
+pub use history::History;
@@ -0,0 +1,190 @@
+// Recordings: games kept to be played again
+
+use game::{Command, Dir, Game};
+use control::{Controller, Script};
+use utypes::{Board, parse_board};
+use topology::Topology;
+use tiling::Tiling;
+
+// commands per line in the text form
+const LINE_LEN: usize = 64;
+
+/// A game as the seed it started with and the commands of every tick,
+/// enough to play it again exactly.
+///
+/// The text form is a few `key value` lines followed by the commands, a
+/// character each: `U`, `D`, `L`, `R` to turn, on hexagons also `7`, `9`,
+/// `1`, `3` for the diagonals (where they are on a numeric keypad), `.`
+/// to go on, `X` to quit.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Recording {
+    pub board: Board,
+    pub snake_len: u16,
+    pub topology: Topology,
+    pub tiling: Tiling,
+    pub hazards: usize,
+    pub seed: u64,
+    pub commands: Vec<Command>,
+}
+
+fn command_char(cmd: Command) -> char {
+    match cmd {
+        Command::Move(Dir::Up) => 'U',
+        Command::Move(Dir::Down) => 'D',
+        Command::Move(Dir::Left) => 'L',
+        Command::Move(Dir::Right) => 'R',
+        Command::Move(Dir::UpLeft) => '7',
+        Command::Move(Dir::UpRight) => '9',
+        Command::Move(Dir::DownLeft) => '1',
+        Command::Move(Dir::DownRight) => '3',
+        Command::Nop => '.',
+        Command::Exit => 'X',
+    }
+}
+
+fn char_command(ch: char) -> Option<Command> {
+    match ch {
+        'U' => Some(Command::Move(Dir::Up)),
+        'D' => Some(Command::Move(Dir::Down)),
+        'L' => Some(Command::Move(Dir::Left)),
+        'R' => Some(Command::Move(Dir::Right)),
+        '7' => Some(Command::Move(Dir::UpLeft)),
+        '9' => Some(Command::Move(Dir::UpRight)),
+        '1' => Some(Command::Move(Dir::DownLeft)),
+        '3' => Some(Command::Move(Dir::DownRight)),
+        '.' => Some(Command::Nop),
+        'X' => Some(Command::Exit),
+        _ => None,
+    }
+}
+
+impl Recording {
+    /// Nothing recorded yet of a game started with these settings, on
+    /// squares and with no hazards unless the `tiling` and `hazards` are
+    /// set.
+    pub fn new(board: Board, snake_len: u16, topology: Topology, seed: u64) -> Recording {
+        Recording {
+            board, snake_len, topology, tiling: Tiling::Square, hazards: 0, seed,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, cmd: Command) {
+        self.commands.push(cmd);
+    }
+
+    /// The game the recording starts with.
+    pub fn game(&self) -> Game {
+        Game::with_seed(self.board, self.snake_len, self.topology, self.seed)
+            .with_tiling(self.tiling)
+            .with_hazards(self.hazards)
+    }
+
+    /// The recorded commands one by one, to replay them with.
+    pub fn script(&self) -> Script {
+        Script::new(&self.commands)
+    }
+
+    /// The game played through to the end of the recording.
+    pub fn replay(&self) -> Game {
+        let mut game = self.game();
+        let mut script = self.script();
+        while game.exec(script.command(&game)).is_some() {}
+        game
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = format!("snake recording 3\n\
+                                board {}x{}\n\
+                                length {}\n\
+                                world {}\n\
+                                grid {}\n\
+                                hazards {}\n\
+                                seed {}\n\
+                                commands\n",
+                               self.board.x, self.board.y,
+                               self.snake_len,
+                               self.topology.name(),
+                               self.tiling.name(),
+                               self.hazards,
+                               self.seed);
+
+        for line in self.commands.chunks(LINE_LEN) {
+            text.extend(line.iter().map(|&cmd| command_char(cmd)));
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// None unless it's all there and makes sense. The recordings from
+    /// before the hexagons (version 1, no `grid`) are on squares, those
+    /// from before the hazards (up to version 2) have none.
+    pub fn parse(text: &str) -> Option<Recording> {
+        let mut lines = text.lines();
+        let version = match lines.next()? {
+            "snake recording 1" => 1,
+            "snake recording 2" => 2,
+            "snake recording 3" => 3,
+            _ => return None,
+        };
+
+        let mut value = |key: &str| {
+            let line = lines.next()?;
+            if line.starts_with(key) && line[key.len() ..].starts_with(' ') {
+                Some(line[key.len() + 1 ..].to_string())
+            } else {
+                None
+            }
+        };
+
+        let board = parse_board(&value("board")?)?;
+        let snake_len = value("length")?.parse().ok()?;
+        let topology = Topology::by_name(&value("world")?)?;
+        let tiling = if version < 2 { Tiling::Square } else { Tiling::by_name(&value("grid")?)? };
+        let hazards = if version < 3 { 0 } else { value("hazards")?.parse().ok()? };
+        let seed = value("seed")?.parse().ok()?;
+        if !topology.fits(board, tiling) || lines.next()? != "commands" {
+            return None;
+        }
+
+        let mut commands = Vec::new();
+        for ch in lines.flat_map(|line| line.chars()) {
+            commands.push(char_command(ch)?);
+        }
+
+        Some(Recording { board, snake_len, topology, tiling, hazards, seed, commands })
+    }
+}
+
+#[test]
+fn recording_simple_test() {
+    use bots;
+
+    let mut game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::WALLS, 7)
+        .with_tiling(Tiling::Hex)
+        .with_hazards(2);
+    let mut recording = Recording::new(game.board(), 4, Topology::WALLS, game.seed());
+    recording.tiling = Tiling::Hex;
+    recording.hazards = 2;
+    let mut bot = bots::Pathfinder::new(0);
+    for _ in 0 .. 100 {
+        let cmd = bot.command(&game);
+        recording.push(cmd);
+        game.exec(cmd);
+    }
+
+    let recording = Recording::parse(&recording.text()).unwrap();
+    assert_eq!(100, recording.commands.len());
+
+    // played again, it ends up the same
+    let replay = recording.replay();
+    assert_eq!(game.snake().into_iter().collect::<Vec<_>>(),
+               replay.snake().into_iter().collect::<Vec<_>>());
+    assert_eq!(game.food().pos, replay.food().pos);
+    assert_eq!(game.hazards(), replay.hazards());
+
+    assert_eq!(None, Recording::parse("snake recording 4\n"));
+    let old = "snake recording 1\nboard 12x10\nlength 4\nworld walls\nseed 7\ncommands\nUL.\n";
+    assert_eq!(Some(Tiling::Square), Recording::parse(old).map(|r| r.tiling));
+}
@@ -0,0 +1,34 @@
+// Renderer interface: anything that shows the game to someone
+
+use utypes::Board;
+use game::Game;
+use game::GameUpdate;
+
+/// A back-end showing the game: a terminal, a recording, a test buffer.
+///
+/// The game loop calls `init` once, then `update` after every tick with
+/// what changed, and `fini` once the game is over. Renderers only ever
+/// read the game, so any of them can be swapped in without touching it.
+pub trait Renderer {
+    /// Draws the whole scene from scratch.
+    fn init(&mut self, game: &Game);
+
+    /// Brings the scene up to date after a tick, `update` being a hint of
+    /// what changed that may as well be ignored.
+    fn update(&mut self, game: &Game, update: &GameUpdate);
+
+    /// The game is over, say goodbye and leave the output tidy.
+    fn fini(&mut self, game: &Game);
+
+    /// The output area changed size (in cells), by default everything is
+    /// just drawn anew.
+    fn resize(&mut self, game: &Game, _size: Board) {
+        self.init(game);
+    }
+
+    /// The game went back to an earlier state (see `History`), no update
+    /// leads there: by default everything is drawn anew.
+    fn rewind(&mut self, game: &Game) {
+        self.init(game);
+    }
+}
@@ -0,0 +1,140 @@
+// Tilings: the shape of the board cells, and which cells are next to
+// each other
+
+use utypes::Pos;
+use game::Dir;
+
+/// What the board is made of.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Tiling {
+    /// Squares, each with four neighbors.
+    Square,
+    /// Hexagons, each with six neighbors. The board is still rows of
+    /// cells, `Pos` being the column and the row, only every odd row is
+    /// half a cell to the right of the even ones: a cell touches two
+    /// cells of the row above and two of the row below.
+    Hex,
+}
+
+/// A hexagon in axial coordinates: `q` goes right along the row, `r`
+/// down the rows and half a cell to the right with every row. Unlike the
+/// rows of the board, the steps are the same wherever the hexagon is.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Hex {
+    pub q: i16,
+    pub r: i16,
+}
+
+const SQUARE_DIRS: [Dir; 4] = [Dir::Down, Dir::Left, Dir::Right, Dir::Up];
+const HEX_DIRS: [Dir; 6] = [Dir::DownLeft, Dir::DownRight, Dir::Left, Dir::Right, Dir::UpLeft,
+                            Dir::UpRight];
+
+// the directions around a cell, clockwise from the top
+const SQUARE_RING: [Dir; 4] = [Dir::Up, Dir::Right, Dir::Down, Dir::Left];
+const HEX_RING: [Dir; 6] = [Dir::UpRight, Dir::Right, Dir::DownRight, Dir::DownLeft, Dir::Left,
+                            Dir::UpLeft];
+
+const TILINGS: [(&str, Tiling); 2] = [("square", Tiling::Square), ("hex", Tiling::Hex)];
+
+// how far right the cells of `row` are pushed, in whole cells, by the
+// half cells of the odd rows from the first one down
+fn half_rows(row: i16) -> i16 {
+    (row - row.rem_euclid(2)) / 2
+}
+
+impl Hex {
+    /// The hexagon of a board cell.
+    pub fn from_pos(pos: Pos) -> Hex {
+        Hex { q: pos.x - half_rows(pos.y), r: pos.y }
+    }
+
+    pub fn to_pos(self) -> Pos {
+        Pos{x: self.q + half_rows(self.r), y: self.r}
+    }
+
+    /// The hexagon next to this one in `dir`, `None` for up and down:
+    /// hexagons have no neighbors straight above or below.
+    pub fn step(self, dir: Dir) -> Option<Hex> {
+        let (q, r) = match dir {
+            Dir::Left => (-1, 0),
+            Dir::Right => (1, 0),
+            Dir::UpLeft => (0, -1),
+            Dir::UpRight => (1, -1),
+            Dir::DownLeft => (-1, 1),
+            Dir::DownRight => (0, 1),
+            Dir::Up | Dir::Down => return None,
+        };
+        Some(Hex { q: self.q + q, r: self.r + r })
+    }
+
+    /// Number of steps to another hexagon.
+    pub fn distance(self, other: Hex) -> i16 {
+        let (q, r) = (self.q - other.q, self.r - other.r);
+        (q.abs() + r.abs() + (q + r).abs()) / 2
+    }
+}
+
+impl Tiling {
+    /// The directions a snake can head in.
+    pub fn dirs(self) -> &'static [Dir] {
+        match self {
+            Tiling::Square => &SQUARE_DIRS,
+            Tiling::Hex => &HEX_DIRS,
+        }
+    }
+
+    // the same, in the order they go round a cell, see `Dir::turn_right`
+    pub(crate) fn ring(self) -> &'static [Dir] {
+        match self {
+            Tiling::Square => &SQUARE_RING,
+            Tiling::Hex => &HEX_RING,
+        }
+    }
+
+    /// The cell next to `pos` in `dir`, maybe off the board; `None` if
+    /// the cells have no neighbor that way.
+    pub fn step(self, pos: Pos, dir: Dir) -> Option<Pos> {
+        match self {
+            Tiling::Square if SQUARE_DIRS.contains(&dir) => Some(pos + dir.into_pos()),
+            Tiling::Square => None,
+            Tiling::Hex => Hex::from_pos(pos).step(dir).map(Hex::to_pos),
+        }
+    }
+
+    /// Number of steps between two cells, the board edges left aside.
+    pub fn distance(self, a: Pos, b: Pos) -> i16 {
+        match self {
+            Tiling::Square => (a.x - b.x).abs() + (a.y - b.y).abs(),
+            Tiling::Hex => Hex::from_pos(a).distance(Hex::from_pos(b)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        TILINGS.iter().find(|t| t.1 == self).unwrap().0
+    }
+
+    pub fn by_name(name: &str) -> Option<Tiling> {
+        TILINGS.iter().find(|t| t.0 == name).map(|t| t.1)
+    }
+}
+
+#[test]
+fn tiling_simple_test() {
+    // the neighbors of a cell on an even row and on an odd one
+    let around = |pos: Pos| Tiling::Hex.dirs().iter()
+        .map(|&dir| Tiling::Hex.step(pos, dir).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![Pos{x: 1, y: 3}, Pos{x: 2, y: 3}, Pos{x: 1, y: 2}, Pos{x: 3, y: 2},
+                    Pos{x: 1, y: 1}, Pos{x: 2, y: 1}], around(Pos{x: 2, y: 2}));
+    assert_eq!(vec![Pos{x: 2, y: 4}, Pos{x: 3, y: 4}, Pos{x: 1, y: 3}, Pos{x: 3, y: 3},
+                    Pos{x: 2, y: 2}, Pos{x: 3, y: 2}], around(Pos{x: 2, y: 3}));
+    assert_eq!(None, Tiling::Hex.step(Pos{x: 2, y: 2}, Dir::Up));
+    assert_eq!(None, Tiling::Square.step(Pos{x: 2, y: 2}, Dir::UpLeft));
+
+    for &pos in [Pos{x: 0, y: 0}, Pos{x: 5, y: 3}, Pos{x: -2, y: -1}].iter() {
+        assert_eq!(pos, Hex::from_pos(pos).to_pos());
+    }
+    assert_eq!(3, Tiling::Hex.distance(Pos{x: 0, y: 0}, Pos{x: 1, y: 3}));
+    assert_eq!(4, Tiling::Square.distance(Pos{x: 0, y: 0}, Pos{x: 1, y: 3}));
+    assert_eq!(Some(Tiling::Hex), Tiling::by_name(Tiling::Hex.name()));
+}
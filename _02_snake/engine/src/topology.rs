@@ -0,0 +1,224 @@
+// Topologies: what lies beyond the edges of the board
+
+use std::cmp;
+
+use utypes::{Board, Pos};
+use tiling::Tiling;
+
+/// What going off an edge of the board leads to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Edge {
+    /// Nothing, the snake runs into a wall.
+    Wall,
+    /// The opposite edge.
+    Wrap,
+    /// The opposite edge upside down: the snake comes back mirrored
+    /// along it, the way it would on a Klein bottle.
+    Twist,
+}
+
+/// The shape of the world: what the left and right edges of the board
+/// lead to (`x`) and what the top and bottom ones do (`y`).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Topology {
+    pub x: Edge,
+    pub y: Edge,
+}
+
+/// The shapes with names of their own, the walls and the torus first.
+pub const NAMED: [(&str, Topology); 6] = [
+    ("walls", Topology::WALLS),
+    ("torus", Topology::TORUS),
+    ("cylinder", Topology { x: Edge::Wrap, y: Edge::Wall }),
+    ("mobius", Topology { x: Edge::Twist, y: Edge::Wall }),
+    ("klein", Topology { x: Edge::Twist, y: Edge::Wrap }),
+    ("projective", Topology { x: Edge::Twist, y: Edge::Twist }),
+];
+
+const EDGES: [(&str, Edge); 3] = [("wall", Edge::Wall), ("wrap", Edge::Wrap), ("twist", Edge::Twist)];
+
+impl Edge {
+    fn name(self) -> &'static str {
+        EDGES.iter().find(|e| e.1 == self).unwrap().0
+    }
+
+    fn by_name(name: &str) -> Option<Edge> {
+        EDGES.iter().find(|e| e.0 == name).map(|e| e.1)
+    }
+
+    /// Whether the board goes on past the edge.
+    pub fn is_open(self) -> bool {
+        self != Edge::Wall
+    }
+}
+
+impl Topology {
+    /// A board with walls all around.
+    pub const WALLS: Topology = Topology { x: Edge::Wall, y: Edge::Wall };
+    /// Every edge leads to the opposite one, what used to be the periodic
+    /// world.
+    pub const TORUS: Topology = Topology { x: Edge::Wrap, y: Edge::Wrap };
+
+    /// The name of the shape, or what its edges do (`wrap/wall`, the left
+    /// and right edges first) if it has none.
+    pub fn name(self) -> String {
+        match NAMED.iter().find(|t| t.1 == self) {
+            Some(&(name, _)) => name.to_string(),
+            None => format!("{}/{}", self.x.name(), self.y.name()),
+        }
+    }
+
+    /// The shape by its name or its edges, `periodic` being the torus.
+    pub fn by_name(name: &str) -> Option<Topology> {
+        if name == "periodic" {
+            return Some(Topology::TORUS);
+        }
+        if let Some(&(_, topology)) = NAMED.iter().find(|t| t.0 == name) {
+            return Some(topology);
+        }
+        let mut edges = name.splitn(2, '/');
+        Some(Topology { x: Edge::by_name(edges.next()?)?, y: Edge::by_name(edges.next()?)? })
+    }
+
+    /// Whether the edges make sense on a board of `tiling`: a cell past
+    /// an edge leads back the way it came. On hexagons, the rows past the
+    /// top and bottom edges have to be pushed right the way the first and
+    /// last rows are: a wrap there takes an even number of rows, a twist
+    /// (on either edges) an odd one.
+    pub fn fits(self, board: Board, tiling: Tiling) -> bool {
+        match tiling {
+            Tiling::Square => true,
+            Tiling::Hex => {
+                let odd = board.y % 2 == 1;
+                let twisted = self.x == Edge::Twist || self.y == Edge::Twist;
+                if odd { self.y != Edge::Wrap } else { !twisted }
+            }
+        }
+    }
+
+    /// Brings a position that went one cell off the board back on it,
+    /// `None` if it ran into a wall.
+    pub fn wrap(self, board: Board, pos: Pos) -> Option<Pos> {
+        let mut pos = pos;
+
+        if pos.x < 0 || pos.x >= board.x {
+            match self.x {
+                Edge::Wall => return None,
+                Edge::Wrap => pos.x = pos.x.rem_euclid(board.x),
+                Edge::Twist => pos = Pos{x: pos.x.rem_euclid(board.x), y: board.y - 1 - pos.y},
+            }
+        }
+        if pos.y < 0 || pos.y >= board.y {
+            match self.y {
+                Edge::Wall => return None,
+                Edge::Wrap => pos.y = pos.y.rem_euclid(board.y),
+                Edge::Twist => pos = Pos{x: board.x - 1 - pos.x, y: pos.y.rem_euclid(board.y)},
+            }
+        }
+
+        Some(pos)
+    }
+
+    /// Number of steps between two cells of a board of `tiling`, going
+    /// through each edge at most once.
+    pub fn distance(self, board: Board, tiling: Tiling, a: Pos, b: Pos) -> i16 {
+        let mut best = tiling.distance(a, b);
+        for &kx in [-1, 0, 1].iter() {
+            for &ky in [-1, 0, 1].iter() {
+                if (kx != 0 && !self.x.is_open()) || (ky != 0 && !self.y.is_open()) {
+                    continue;
+                }
+
+                // where `b` seems to be seen through the edges
+                let mut seen = b;
+                if kx != 0 && self.x == Edge::Twist {
+                    seen.y = board.y - 1 - seen.y;
+                }
+                if ky != 0 && self.y == Edge::Twist {
+                    seen.x = board.x - 1 - seen.x;
+                }
+                seen = seen + Pos{x: kx * board.x, y: ky * board.y};
+                best = cmp::min(best, tiling.distance(a, seen));
+            }
+        }
+        best
+    }
+}
+
+#[test]
+fn topology_simple_test() {
+    let board = Board{x: 10, y: 6};
+    let klein = Topology::by_name("klein").unwrap();
+
+    assert_eq!(None, Topology::WALLS.wrap(board, Pos{x: -1, y: 2}));
+    assert_eq!(Some(Pos{x: 9, y: 2}), Topology::TORUS.wrap(board, Pos{x: -1, y: 2}));
+    assert_eq!(Some(Pos{x: 9, y: 3}), klein.wrap(board, Pos{x: -1, y: 2}));
+    assert_eq!(Some(Pos{x: 4, y: 0}), klein.wrap(board, Pos{x: 4, y: 6}));
+    assert_eq!(Some(Pos{x: 5, y: 0}), Topology::by_name("projective").unwrap().wrap(board, Pos{x: 4, y: 6}));
+
+    assert_eq!(2, Topology::TORUS.distance(board, Tiling::Square, Pos{x: 0, y: 0}, Pos{x: 9, y: 5}));
+    assert_eq!(1, klein.distance(board, Tiling::Square, Pos{x: 0, y: 0}, Pos{x: 9, y: 5}));
+    assert_eq!(14, Topology::WALLS.distance(board, Tiling::Square, Pos{x: 0, y: 0}, Pos{x: 9, y: 5}));
+
+    for &(name, topology) in NAMED.iter() {
+        assert_eq!(name, topology.name());
+        assert_eq!(Some(topology), Topology::by_name(name));
+    }
+    let odd = Topology { x: Edge::Wall, y: Edge::Twist };
+    assert_eq!(Some(odd), Topology::by_name(&odd.name()));
+    assert_eq!(Some(Topology::TORUS), Topology::by_name("periodic"));
+    assert_eq!(None, Topology::by_name("wrap/"));
+}
+
+// On every board that fits, the cells next to a cell have it next to
+// them, and the distance is what a search through them makes it.
+#[test]
+fn topology_distance_test() {
+    use std::collections::VecDeque;
+
+    let edges = [Edge::Wall, Edge::Wrap, Edge::Twist];
+    let klein = Topology::by_name("klein").unwrap();
+    assert!(!klein.fits(Board{x: 6, y: 7}, Tiling::Hex));
+    assert!(!klein.fits(Board{x: 7, y: 6}, Tiling::Hex));
+
+    for &tiling in [Tiling::Square, Tiling::Hex].iter() {
+        for &board in [Board{x: 7, y: 6}, Board{x: 6, y: 7}].iter() {
+            for &x in edges.iter() {
+                for &y in edges.iter() {
+                    let topology = Topology { x, y };
+                    if !topology.fits(board, tiling) {
+                        continue;
+                    }
+
+                    let around = |pos: Pos| tiling.dirs().iter()
+                        .filter_map(|&dir| topology.wrap(board, tiling.step(pos, dir)?))
+                        .collect::<Vec<_>>();
+                    let index = |pos: Pos| (pos.y * board.x + pos.x) as usize;
+                    let cells: Vec<Pos> = (0 .. board.y)
+                        .flat_map(|y| (0 .. board.x).map(move |x| Pos{x, y}))
+                        .collect();
+                    for &a in &cells {
+                        let mut steps = vec![-1; cells.len()];
+                        steps[index(a)] = 0;
+                        let mut queue = VecDeque::new();
+                        queue.push_back(a);
+                        while let Some(pos) = queue.pop_front() {
+                            for next in around(pos) {
+                                assert!(around(next).contains(&pos),
+                                        "{:?} {}", tiling, topology.name());
+                                if steps[index(next)] < 0 {
+                                    steps[index(next)] = steps[index(pos)] + 1;
+                                    queue.push_back(next);
+                                }
+                            }
+                        }
+                        for &b in &cells {
+                            assert_eq!(steps[index(b)], topology.distance(board, tiling, a, b),
+                                       "{:?} {} {:?} {:?}", tiling, topology.name(), a, b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -4,14 +4,27 @@ use std::ops::Add;
 use std::ops::Sub;
 use std::ops::Mul;
 
+/// A cell on the board, or a shift from one cell to another.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Pos {
     pub x: i16,
     pub y: i16,
 }
 
+/// Board size in cells.
 pub type Board = Pos;
 
+/// Reads a board size written as `WxH`, e.g. `16x16`.
+pub fn parse_board(s: &str) -> Option<Board> {
+    let mut it = s.split('x');
+    let x = it.next()?.parse().ok()?;
+    let y = it.next()?.parse().ok()?;
+    if it.next().is_some() || x <= 4 || y <= 4 {
+        return None;
+    }
+    Some(Board{x, y})
+}
+
 impl Add for Pos {
     type Output = Pos;
 
@@ -43,3 +56,11 @@ fn pos_simple_test() {
     assert_eq!(Pos{x: 4, y: -6}, 2 * Pos{x: 2, y: -3});
     assert_eq!(Pos{x: -4, y: 6}, -2 * Pos{x: 2, y: -3});
 }
+
+#[test]
+fn parse_board_test() {
+    assert_eq!(Some(Board{x: 16, y: 9}), parse_board("16x9"));
+    assert_eq!(None, parse_board("16"));
+    assert_eq!(None, parse_board("16x9x2"));
+    assert_eq!(None, parse_board("4x9"));
+}
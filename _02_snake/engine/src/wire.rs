@@ -0,0 +1,1000 @@
+// Wire protocol: the messages between the game server and its clients,
+// also what replay files and bot processes are made of
+//
+// This is version 5. Each message comes in one of two encodings.
+//
+// Binary, the compact one. A message is a frame: the length of the body
+// (u32), then the body, at most MAX_FRAME bytes. The body is the kind of
+// the message (u8) and its fields. Numbers are big-endian, positions and
+// sizes are two i16 (x, y), players are u16.
+//
+//   1 hello     "SNAK", min version u8, max version u8,
+//               board wish u8 (0: none, 1: followed by the size),
+//               watch u8 (1: only to watch; since version 2, 0 if missing)
+//   2 welcome   version u8, player u16 (0xffff: none, only watching),
+//               players u16, board size, world u8, tick length in ms u32
+//   3 reject    why: length u16 and UTF-8 text
+//   4 snapshot  board size, world u8, food position, players u16 and
+//               for each of them why they are out (u8 reason, 0 if still
+//               in), then snakes u16 and for each of them: player u16,
+//               color u8, dir u8, length u32 and the cells head first,
+//               then hazards u16 and for each of them its position and
+//               motion u8 (since version 5, none if missing)
+//   5 tick      events u16, each of them one of
+//                 1 moved: player u16, head position, dir u8, grew u8
+//                 2 food: position
+//                 3 out: player u16, reason u8
+//                 4 hazard: hazard u16, position, motion u8
+//   6 command   command u8
+//
+// Codes: dir 0 down, 1 left, 2 right, 3 up, and on hexagons (since
+// version 4) 4 down-left, 5 down-right, 6 up-left, 7 up-right; color
+// 0 white, 1 blue, 2 green, 3 red; reason 1 exit, 2 wall, 3 self,
+// 4 snake, 5 full, 6 hazard; motion 0 chase, 1 + dir to patrol;
+// command 0 nop, 1 exit, 2 + dir to move; world
+// 0 walls, 1 torus (the only two before version 3), 2 cylinder, 3 mobius,
+// 4 klein, 5 projective, 6 wall/wrap, 7 wall/twist, 8 wrap/twist (see
+// `Topology::name`), plus 16 on hexagons (since version 4).
+//
+// JSON, to debug with and for bots: a message is an object on a line of
+// its own, the kind in "type", the fields by name, positions and sizes
+// as `[x, y]`, the codes above by name (see `Message::json`), the
+// tiling in a "grid" of its own ("square" if it's missing), a hazard
+// as its "pos" and "motion", "chase" or "patrol" along a "dir". A
+// connection speaks JSON if its first byte is `{`.
+//
+// A session: the client says hello with the versions it speaks and the
+// board it would like to play on. The server answers with a welcome in
+// the highest version both speak (or with a reject, closing the
+// connection), and once the game starts, with a snapshot of it followed
+// by a tick message every tick. The client sends commands whenever the
+// player changes their mind, the last one before a tick counts.
+//
+// Each side puts its messages the way the version they settled on does,
+// for the fields that version has. A server doesn't take in those whose
+// version can't tell of its game at all (see `oldest_version`).
+//
+// A client can say hello to watch instead. It is welcomed as no player,
+// gets the snapshot as soon as the game is on (right away if it already
+// is) and the ticks after it, and has nothing to send.
+//
+// A replay file is what a client only watching gets: the welcome, the
+// snapshot and the ticks, binary.
+
+#[cfg(test)]
+extern crate rand;
+
+use std::convert::TryFrom;
+use std::io;
+use std::io::{BufRead, Read, Write};
+
+use game::{Color, Command, Dir, Event, GameOver, Hazard, Motion, SnakeState, Snapshot};
+use json::Value;
+use utypes::{Board, Pos};
+use topology::{Edge, Topology};
+use tiling::Tiling;
+
+/// The protocol version spoken here, and the oldest one still understood.
+pub const VERSION: u8 = 5;
+pub const MIN_VERSION: u8 = 1;
+
+/// Where the server listens unless told otherwise.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+/// The largest binary message body.
+pub const MAX_FRAME: usize = 1 << 20;
+
+const MAGIC: &[u8] = b"SNAK";
+const NO_PLAYER: u16 = 0xffff;
+
+const COLORS: [Color; 4] = [Color::White, Color::Blue, Color::Green, Color::Red];
+const REASONS: [GameOver; 6] = [GameOver::Exit, GameOver::HitWall, GameOver::HitSelf,
+                                GameOver::HitSnake, GameOver::BoardFull, GameOver::HitHazard];
+
+/// How the messages of a connection (or a file) are put.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Encoding {
+    Binary,
+    Json,
+}
+
+/// The server's side of the handshake: what the game is like and which
+/// of the snakes is the client's.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Welcome {
+    pub version: u8,
+    /// `None` for those only watching.
+    pub player: Option<usize>,
+    pub players: usize,
+    pub board: Board,
+    pub topology: Topology,
+    pub tiling: Tiling,
+    pub tick_ms: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Message {
+    /// Client to server, first thing: the versions it speaks, the board
+    /// it would like, whether it is only there to watch.
+    Hello { min_version: u8, max_version: u8, board: Option<Board>, watch: bool },
+    /// Server to client, the client is in.
+    Welcome(Welcome),
+    /// Server to client, the client is not in, and why.
+    Reject(String),
+    /// Server to client, the game as it is.
+    Snapshot(Snapshot),
+    /// Server to client, what a tick did.
+    Tick(Vec<Event>),
+    /// Client to server, what the player wants their snake to do.
+    Command(Command),
+}
+
+/// The version to speak with someone speaking `min ..= max`, `None` if
+/// there is none in common.
+pub fn negotiate(min: u8, max: u8) -> Option<u8> {
+    let version = max.min(VERSION);
+    if version >= min.max(MIN_VERSION) { Some(version) } else { None }
+}
+
+/// The oldest version that can tell of a game in this world, with or
+/// without hazards.
+pub fn oldest_version(topology: Topology, tiling: Tiling, hazards: bool) -> u8 {
+    if hazards {
+        5
+    } else if tiling != Tiling::Square {
+        4
+    } else if topology != Topology::WALLS && topology != Topology::TORUS {
+        3
+    } else {
+        MIN_VERSION
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed message")
+}
+
+impl Encoding {
+    /// Tells the encoding by the first byte there is to read, without
+    /// taking it.
+    pub fn detect(input: &mut dyn BufRead) -> io::Result<Encoding> {
+        match input.fill_buf()?.first() {
+            Some(b'{') => Ok(Encoding::Json),
+            Some(_) => Ok(Encoding::Binary),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "nothing to read")),
+        }
+    }
+}
+
+// the codes and names of the things in the messages
+
+// the directions by their codes
+const DIRS: [Dir; 8] = [Dir::Down, Dir::Left, Dir::Right, Dir::Up,
+                        Dir::DownLeft, Dir::DownRight, Dir::UpLeft, Dir::UpRight];
+
+fn dir_code(dir: Dir) -> u8 {
+    DIRS.iter().position(|&d| d == dir).unwrap() as u8
+}
+
+fn dir_name(dir: Dir) -> &'static str {
+    match dir {
+        Dir::Down => "down",
+        Dir::Left => "left",
+        Dir::Right => "right",
+        Dir::Up => "up",
+        Dir::DownLeft => "down-left",
+        Dir::DownRight => "down-right",
+        Dir::UpLeft => "up-left",
+        Dir::UpRight => "up-right",
+    }
+}
+
+fn dir_by_name(name: &str) -> Option<Dir> {
+    DIRS.iter().cloned().find(|&d| dir_name(d) == name)
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Blue => "blue",
+        Color::Green => "green",
+        Color::Red => "red",
+    }
+}
+
+fn reason_name(reason: GameOver) -> &'static str {
+    match reason {
+        GameOver::Exit => "exit",
+        GameOver::HitWall => "wall",
+        GameOver::HitSelf => "self",
+        GameOver::HitSnake => "snake",
+        GameOver::BoardFull => "full",
+        GameOver::HitHazard => "hazard",
+    }
+}
+
+fn motion_code(motion: Motion) -> u8 {
+    match motion {
+        Motion::Chase => 0,
+        Motion::Patrol(dir) => 1 + dir_code(dir),
+    }
+}
+
+// the worlds by their codes
+const WORLDS: [Topology; 9] = [
+    Topology::WALLS,
+    Topology::TORUS,
+    Topology { x: Edge::Wrap, y: Edge::Wall },
+    Topology { x: Edge::Twist, y: Edge::Wall },
+    Topology { x: Edge::Twist, y: Edge::Wrap },
+    Topology { x: Edge::Twist, y: Edge::Twist },
+    Topology { x: Edge::Wall, y: Edge::Wrap },
+    Topology { x: Edge::Wall, y: Edge::Twist },
+    Topology { x: Edge::Wrap, y: Edge::Twist },
+];
+
+// added to the world code on hexagons
+const HEX_WORLD: u8 = 16;
+
+fn world_code(topology: Topology, tiling: Tiling) -> u8 {
+    let code = WORLDS.iter().position(|&t| t == topology).unwrap() as u8;
+    if tiling == Tiling::Hex { code + HEX_WORLD } else { code }
+}
+
+fn command_code(cmd: Command) -> u8 {
+    match cmd {
+        Command::Nop => 0,
+        Command::Exit => 1,
+        Command::Move(dir) => 2 + dir_code(dir),
+    }
+}
+
+fn command_by_code(code: u8) -> Option<Command> {
+    match code {
+        0 => Some(Command::Nop),
+        1 => Some(Command::Exit),
+        _ => DIRS.get(code as usize - 2).map(|&dir| Command::Move(dir)),
+    }
+}
+
+/// The name of a command in JSON: `nop`, `exit`, or the direction.
+pub fn command_name(cmd: Command) -> &'static str {
+    match cmd {
+        Command::Nop => "nop",
+        Command::Exit => "exit",
+        Command::Move(dir) => dir_name(dir),
+    }
+}
+
+pub fn command_by_name(name: &str) -> Option<Command> {
+    match name {
+        "nop" => Some(Command::Nop),
+        "exit" => Some(Command::Exit),
+        _ => dir_by_name(name).map(Command::Move),
+    }
+}
+
+// binary
+
+fn put_u16(out: &mut Vec<u8>, n: u16) {
+    out.extend_from_slice(&n.to_be_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_be_bytes());
+}
+
+fn put_pos(out: &mut Vec<u8>, pos: Pos) {
+    out.extend_from_slice(&pos.x.to_be_bytes());
+    out.extend_from_slice(&pos.y.to_be_bytes());
+}
+
+fn put_player(out: &mut Vec<u8>, player: usize) {
+    put_u16(out, player as u16);
+}
+
+fn put_hazard(out: &mut Vec<u8>, hazard: Hazard) {
+    put_pos(out, hazard.pos);
+    out.push(motion_code(hazard.motion));
+}
+
+// what is left of a message body to read
+struct Bytes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.data.len() {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn pos(&mut self) -> Option<Pos> {
+        let b = self.take(4)?;
+        Some(Pos{x: i16::from_be_bytes([b[0], b[1]]), y: i16::from_be_bytes([b[2], b[3]])})
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn world(&mut self) -> Option<(Topology, Tiling)> {
+        match self.u8()? {
+            code if code >= HEX_WORLD => Some((*WORLDS.get((code - HEX_WORLD) as usize)?, Tiling::Hex)),
+            code => Some((*WORLDS.get(code as usize)?, Tiling::Square)),
+        }
+    }
+
+    fn dir(&mut self) -> Option<Dir> {
+        DIRS.get(self.u8()? as usize).cloned()
+    }
+
+    fn reason(&mut self) -> Option<GameOver> {
+        REASONS.get((self.u8()? as usize).checked_sub(1)?).cloned()
+    }
+
+    fn motion(&mut self) -> Option<Motion> {
+        match self.u8()? {
+            0 => Some(Motion::Chase),
+            code => DIRS.get(code as usize - 1).map(|&dir| Motion::Patrol(dir)),
+        }
+    }
+
+    fn hazard(&mut self) -> Option<Hazard> {
+        Some(Hazard { pos: self.pos()?, motion: self.motion()? })
+    }
+
+    // room for `n` things of `size` bytes, no more than there are left
+    fn capacity(&self, n: usize, size: usize) -> usize {
+        n.min(self.data.len() / size)
+    }
+}
+
+// json
+
+fn int(n: i64) -> Value {
+    Value::Int(n)
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(s.to_string())
+}
+
+fn pos_value(pos: Pos) -> Value {
+    Value::Array(vec![int(pos.x as i64), int(pos.y as i64)])
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn json_world(value: &Value) -> Option<Topology> {
+    Topology::by_name(value.str()?)
+}
+
+fn json_grid(value: Option<&Value>) -> Option<Tiling> {
+    match value {
+        Some(value) => Tiling::by_name(value.str()?),
+        None => Some(Tiling::Square),
+    }
+}
+
+fn json_int<T: TryFrom<i64>>(value: &Value) -> Option<T> {
+    T::try_from(value.int()?).ok()
+}
+
+fn json_pos(value: &Value) -> Option<Pos> {
+    match value.array()? {
+        [x, y] => Some(Pos{x: json_int(x)?, y: json_int(y)?}),
+        _ => None,
+    }
+}
+
+fn json_reason(value: &Value) -> Option<GameOver> {
+    let name = value.str()?;
+    REASONS.iter().cloned().find(|&r| reason_name(r) == name)
+}
+
+// the fields of a hazard, after those of whatever it is in
+fn hazard_fields(fields: &mut Vec<(&str, Value)>, hazard: Hazard) {
+    fields.push(("pos", pos_value(hazard.pos)));
+    match hazard.motion {
+        Motion::Chase => fields.push(("motion", string("chase"))),
+        Motion::Patrol(dir) => {
+            fields.push(("motion", string("patrol")));
+            fields.push(("dir", string(dir_name(dir))));
+        }
+    }
+}
+
+fn json_hazard(value: &Value) -> Option<Hazard> {
+    let motion = match value.get("motion")?.str()? {
+        "chase" => Motion::Chase,
+        "patrol" => Motion::Patrol(dir_by_name(value.get("dir")?.str()?)?),
+        _ => return None,
+    };
+    Some(Hazard { pos: json_pos(value.get("pos")?)?, motion })
+}
+
+impl Message {
+    /// The binary body of the message (without the length in front), as
+    /// `version` puts it.
+    pub fn encode(&self, version: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        match *self {
+            Message::Hello { min_version, max_version, board, watch } => {
+                out.push(1);
+                out.extend_from_slice(MAGIC);
+                out.push(min_version);
+                out.push(max_version);
+                match board {
+                    Some(board) => {
+                        out.push(1);
+                        put_pos(&mut out, board);
+                    }
+                    None => out.push(0),
+                }
+                if version >= 2 {
+                    out.push(watch as u8);
+                }
+            }
+            Message::Welcome(ref w) => {
+                out.push(2);
+                out.push(w.version);
+                put_u16(&mut out, w.player.map_or(NO_PLAYER, |p| p as u16));
+                put_player(&mut out, w.players);
+                put_pos(&mut out, w.board);
+                out.push(world_code(w.topology, w.tiling));
+                put_u32(&mut out, w.tick_ms);
+            }
+            Message::Reject(ref why) => {
+                out.push(3);
+                let why = &why.as_bytes()[.. why.len().min(u16::MAX as usize)];
+                put_u16(&mut out, why.len() as u16);
+                out.extend_from_slice(why);
+            }
+            Message::Snapshot(ref s) => {
+                out.push(4);
+                put_pos(&mut out, s.board);
+                out.push(world_code(s.topology, s.tiling));
+                put_pos(&mut out, s.food);
+                put_player(&mut out, s.outs.len());
+                for out_reason in &s.outs {
+                    out.push(out_reason.map_or(0, |r| REASONS.iter().position(|&x| x == r).unwrap() as u8 + 1));
+                }
+                put_u16(&mut out, s.snakes.len() as u16);
+                for snake in &s.snakes {
+                    put_player(&mut out, snake.player);
+                    out.push(COLORS.iter().position(|&c| c == snake.color).unwrap() as u8);
+                    out.push(dir_code(snake.dir));
+                    put_u32(&mut out, snake.body.len() as u32);
+                    for &p in &snake.body {
+                        put_pos(&mut out, p);
+                    }
+                }
+                if version >= 5 {
+                    put_u16(&mut out, s.hazards.len() as u16);
+                    for &hazard in &s.hazards {
+                        put_hazard(&mut out, hazard);
+                    }
+                }
+            }
+            Message::Tick(ref events) => {
+                out.push(5);
+                put_u16(&mut out, events.len() as u16);
+                for event in events {
+                    match *event {
+                        Event::Moved { player, head, dir, grew } => {
+                            out.push(1);
+                            put_player(&mut out, player);
+                            put_pos(&mut out, head);
+                            out.push(dir_code(dir));
+                            out.push(grew as u8);
+                        }
+                        Event::Food(pos) => {
+                            out.push(2);
+                            put_pos(&mut out, pos);
+                        }
+                        Event::Out { player, reason } => {
+                            out.push(3);
+                            put_player(&mut out, player);
+                            out.push(REASONS.iter().position(|&r| r == reason).unwrap() as u8 + 1);
+                        }
+                        Event::Hazard { hazard, pos, motion } => {
+                            out.push(4);
+                            put_u16(&mut out, hazard as u16);
+                            put_hazard(&mut out, Hazard { pos, motion });
+                        }
+                    }
+                }
+            }
+            Message::Command(cmd) => {
+                out.push(6);
+                out.push(command_code(cmd));
+            }
+        }
+        out
+    }
+
+    /// The message of a binary body, `None` unless the body is all of a
+    /// whole message.
+    pub fn decode(body: &[u8]) -> Option<Message> {
+        let mut b = Bytes { data: body };
+        let message = match b.u8()? {
+            1 => {
+                if b.take(MAGIC.len())? != MAGIC {
+                    return None;
+                }
+                let min_version = b.u8()?;
+                let max_version = b.u8()?;
+                let board = if b.bool()? { Some(b.pos()?) } else { None };
+                let watch = if b.data.is_empty() { false } else { b.bool()? };
+                Message::Hello { min_version, max_version, board, watch }
+            }
+            2 => {
+                let version = b.u8()?;
+                let player = match b.u16()? {
+                    NO_PLAYER => None,
+                    p => Some(p as usize),
+                };
+                let players = b.u16()? as usize;
+                let board = b.pos()?;
+                let (topology, tiling) = b.world()?;
+                let tick_ms = b.u32()?;
+                Message::Welcome(Welcome { version, player, players, board, topology, tiling, tick_ms })
+            }
+            3 => {
+                let len = b.u16()? as usize;
+                Message::Reject(String::from_utf8(b.take(len)?.to_vec()).ok()?)
+            }
+            4 => {
+                let board = b.pos()?;
+                let (topology, tiling) = b.world()?;
+                let food = b.pos()?;
+                let players = b.u16()? as usize;
+                let mut outs = Vec::with_capacity(b.capacity(players, 1));
+                for _ in 0 .. players {
+                    outs.push(match b.u8()? {
+                        0 => None,
+                        code => Some(*REASONS.get(code as usize - 1)?),
+                    });
+                }
+                let count = b.u16()? as usize;
+                let mut snakes = Vec::with_capacity(b.capacity(count, 12));
+                for _ in 0 .. count {
+                    let player = b.u16()? as usize;
+                    let color = *COLORS.get(b.u8()? as usize)?;
+                    let dir = b.dir()?;
+                    let len = b.u32()? as usize;
+                    let mut body = Vec::with_capacity(b.capacity(len, 4));
+                    for _ in 0 .. len {
+                        body.push(b.pos()?);
+                    }
+                    snakes.push(SnakeState { player, color, dir, body });
+                }
+                let count = if b.data.is_empty() { 0 } else { b.u16()? as usize };
+                let mut hazards = Vec::with_capacity(b.capacity(count, 5));
+                for _ in 0 .. count {
+                    hazards.push(b.hazard()?);
+                }
+                Message::Snapshot(Snapshot { board, topology, tiling, snakes, food, hazards, outs })
+            }
+            5 => {
+                let count = b.u16()? as usize;
+                let mut events = Vec::with_capacity(b.capacity(count, 3));
+                for _ in 0 .. count {
+                    events.push(match b.u8()? {
+                        1 => Event::Moved {
+                            player: b.u16()? as usize,
+                            head: b.pos()?,
+                            dir: b.dir()?,
+                            grew: b.bool()?,
+                        },
+                        2 => Event::Food(b.pos()?),
+                        3 => Event::Out { player: b.u16()? as usize, reason: b.reason()? },
+                        4 => {
+                            let hazard = b.u16()? as usize;
+                            let Hazard { pos, motion } = b.hazard()?;
+                            Event::Hazard { hazard, pos, motion }
+                        }
+                        _ => return None,
+                    });
+                }
+                Message::Tick(events)
+            }
+            6 => Message::Command(command_by_code(b.u8()?)?),
+            _ => return None,
+        };
+
+        if b.data.is_empty() { Some(message) } else { None }
+    }
+
+    /// The message as a line of JSON (without the newline), as `version`
+    /// puts it.
+    pub fn json(&self, version: u8) -> String {
+        let mut value = match *self {
+            Message::Hello { min_version, max_version, board, watch } => object(vec![
+                ("type", string("hello")),
+                ("min_version", int(min_version as i64)),
+                ("max_version", int(max_version as i64)),
+                ("board", board.map_or(Value::Null, pos_value)),
+                ("watch", Value::Bool(watch)),
+            ]),
+            Message::Welcome(ref w) => object(vec![
+                ("type", string("welcome")),
+                ("version", int(w.version as i64)),
+                ("player", w.player.map_or(Value::Null, |p| int(p as i64))),
+                ("players", int(w.players as i64)),
+                ("board", pos_value(w.board)),
+                ("world", string(&w.topology.name())),
+                ("grid", string(w.tiling.name())),
+                ("tick_ms", int(w.tick_ms as i64)),
+            ]),
+            Message::Reject(ref why) => object(vec![
+                ("type", string("reject")),
+                ("why", string(why)),
+            ]),
+            Message::Snapshot(ref s) => object(vec![
+                ("type", string("snapshot")),
+                ("board", pos_value(s.board)),
+                ("world", string(&s.topology.name())),
+                ("grid", string(s.tiling.name())),
+                ("food", pos_value(s.food)),
+                ("outs", Value::Array(s.outs.iter()
+                    .map(|o| o.map_or(Value::Null, |r| string(reason_name(r)))).collect())),
+                ("snakes", Value::Array(s.snakes.iter().map(|snake| object(vec![
+                    ("player", int(snake.player as i64)),
+                    ("color", string(color_name(snake.color))),
+                    ("dir", string(dir_name(snake.dir))),
+                    ("body", Value::Array(snake.body.iter().map(|&p| pos_value(p)).collect())),
+                ])).collect())),
+                ("hazards", Value::Array(s.hazards.iter().map(|&hazard| {
+                    let mut fields = Vec::new();
+                    hazard_fields(&mut fields, hazard);
+                    object(fields)
+                }).collect())),
+            ]),
+            Message::Tick(ref events) => object(vec![
+                ("type", string("tick")),
+                ("events", Value::Array(events.iter().map(|event| match *event {
+                    Event::Moved { player, head, dir, grew } => object(vec![
+                        ("event", string("moved")),
+                        ("player", int(player as i64)),
+                        ("head", pos_value(head)),
+                        ("dir", string(dir_name(dir))),
+                        ("grew", Value::Bool(grew)),
+                    ]),
+                    Event::Food(pos) => object(vec![
+                        ("event", string("food")),
+                        ("pos", pos_value(pos)),
+                    ]),
+                    Event::Out { player, reason } => object(vec![
+                        ("event", string("out")),
+                        ("player", int(player as i64)),
+                        ("reason", string(reason_name(reason))),
+                    ]),
+                    Event::Hazard { hazard, pos, motion } => {
+                        let mut fields = vec![
+                            ("event", string("hazard")),
+                            ("hazard", int(hazard as i64)),
+                        ];
+                        hazard_fields(&mut fields, Hazard { pos, motion });
+                        object(fields)
+                    }
+                }).collect())),
+            ]),
+            Message::Command(cmd) => object(vec![
+                ("type", string("command")),
+                ("command", string(command_name(cmd))),
+            ]),
+        };
+        if let Value::Object(ref mut fields) = value {
+            fields.retain(|field| match field.0.as_str() {
+                "watch" => version >= 2,
+                "grid" => version >= 4,
+                "hazards" => version >= 5,
+                _ => true,
+            });
+        }
+        value.to_string()
+    }
+
+    /// The message of a line of JSON, `None` unless it makes sense.
+    pub fn parse_json(line: &str) -> Option<Message> {
+        let v = Value::parse(line)?;
+        let field = |key: &str| v.get(key);
+
+        Some(match field("type")?.str()? {
+            "hello" => Message::Hello {
+                min_version: json_int(field("min_version")?)?,
+                max_version: json_int(field("max_version")?)?,
+                board: match *field("board")? {
+                    Value::Null => None,
+                    ref board => Some(json_pos(board)?),
+                },
+                watch: match field("watch") {
+                    Some(watch) => watch.bool()?,
+                    None => false,
+                },
+            },
+            "welcome" => Message::Welcome(Welcome {
+                version: json_int(field("version")?)?,
+                player: match *field("player")? {
+                    Value::Null => None,
+                    ref player => Some(json_int(player)?),
+                },
+                players: json_int(field("players")?)?,
+                board: json_pos(field("board")?)?,
+                topology: json_world(field("world")?)?,
+                tiling: json_grid(field("grid"))?,
+                tick_ms: json_int(field("tick_ms")?)?,
+            }),
+            "reject" => Message::Reject(field("why")?.str()?.to_string()),
+            "snapshot" => {
+                let mut outs = Vec::new();
+                for out in field("outs")?.array()? {
+                    outs.push(match *out {
+                        Value::Null => None,
+                        ref reason => Some(json_reason(reason)?),
+                    });
+                }
+                let mut snakes = Vec::new();
+                for snake in field("snakes")?.array()? {
+                    let color = snake.get("color")?.str()?;
+                    snakes.push(SnakeState {
+                        player: json_int(snake.get("player")?)?,
+                        color: *COLORS.iter().find(|&&c| color_name(c) == color)?,
+                        dir: dir_by_name(snake.get("dir")?.str()?)?,
+                        body: snake.get("body")?.array()?.iter().map(json_pos)
+                            .collect::<Option<Vec<Pos>>>()?,
+                    });
+                }
+                let hazards = match field("hazards") {
+                    Some(hazards) => {
+                        hazards.array()?.iter().map(json_hazard).collect::<Option<Vec<_>>>()?
+                    }
+                    None => Vec::new(),
+                };
+                Message::Snapshot(Snapshot {
+                    board: json_pos(field("board")?)?,
+                    topology: json_world(field("world")?)?,
+                    tiling: json_grid(field("grid"))?,
+                    snakes,
+                    food: json_pos(field("food")?)?,
+                    hazards,
+                    outs,
+                })
+            }
+            "tick" => {
+                let mut events = Vec::new();
+                for event in field("events")?.array()? {
+                    let get = |key: &str| event.get(key);
+                    events.push(match get("event")?.str()? {
+                        "moved" => Event::Moved {
+                            player: json_int(get("player")?)?,
+                            head: json_pos(get("head")?)?,
+                            dir: dir_by_name(get("dir")?.str()?)?,
+                            grew: get("grew")?.bool()?,
+                        },
+                        "food" => Event::Food(json_pos(get("pos")?)?),
+                        "out" => Event::Out {
+                            player: json_int(get("player")?)?,
+                            reason: json_reason(get("reason")?)?,
+                        },
+                        "hazard" => {
+                            let Hazard { pos, motion } = json_hazard(event)?;
+                            Event::Hazard { hazard: json_int(get("hazard")?)?, pos, motion }
+                        }
+                        _ => return None,
+                    });
+                }
+                Message::Tick(events)
+            }
+            "command" => Message::Command(command_by_name(field("command")?.str()?)?),
+            _ => return None,
+        })
+    }
+
+    /// Writes the message down as `version` puts it.
+    pub fn write(&self, out: &mut dyn Write, encoding: Encoding, version: u8) -> io::Result<()> {
+        match encoding {
+            Encoding::Binary => {
+                let body = self.encode(version);
+                let mut frame = Vec::with_capacity(4 + body.len());
+                put_u32(&mut frame, body.len() as u32);
+                frame.extend_from_slice(&body);
+                out.write_all(&frame)
+            }
+            Encoding::Json => out.write_all(format!("{}\n", self.json(version)).as_bytes()),
+        }
+    }
+
+    /// The next message, an `UnexpectedEof` error if there are no more.
+    pub fn read(input: &mut dyn BufRead, encoding: Encoding) -> io::Result<Message> {
+        match encoding {
+            Encoding::Binary => {
+                let mut len = [0u8; 4];
+                input.read_exact(&mut len)?;
+                let len = u32::from_be_bytes(len) as usize;
+                if len > MAX_FRAME {
+                    return Err(malformed());
+                }
+                let mut body = Vec::new();
+                input.take(len as u64).read_to_end(&mut body)?;
+                if body.len() < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Message::decode(&body).ok_or_else(malformed)
+            }
+            Encoding::Json => {
+                let mut line = String::new();
+                // no line is that long, but a stream of junk could be
+                if input.take(MAX_FRAME as u64).read_line(&mut line)? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Message::parse_json(line.trim_end()).ok_or_else(malformed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn sample_messages() -> Vec<Message> {
+    use game::Game;
+
+    let mut game = Game::with_players(Board{x: 16, y: 12}, 4, Topology::TORUS, 5, 2).with_hazards(3);
+    game.exec_all(&[Command::Move(Dir::Up), Command::Exit]);
+
+    vec![
+        Message::Hello {
+            min_version: 1, max_version: 3, board: Some(Board{x: 20, y: 10}), watch: true,
+        },
+        Message::Welcome(Welcome {
+            version: 1, player: Some(1), players: 2, board: Board{x: 16, y: 12},
+            topology: Topology::WALLS, tiling: Tiling::Hex, tick_ms: 200,
+        }),
+        Message::Reject("no \"room\"".to_string()),
+        Message::Snapshot(game.snapshot()),
+        Message::Tick(game.events().to_vec()),
+        Message::Command(Command::Move(Dir::Left)),
+        Message::Command(Command::Move(Dir::UpRight)),
+    ]
+}
+
+#[test]
+fn wire_simple_test() {
+    use game::Game;
+
+    let messages = sample_messages();
+
+    for encoding in &[Encoding::Binary, Encoding::Json] {
+        let mut out = Vec::new();
+        for message in &messages {
+            message.write(&mut out, *encoding, VERSION).unwrap();
+        }
+
+        let mut input = &out[..];
+        assert_eq!(*encoding, Encoding::detect(&mut input).unwrap());
+        for message in &messages {
+            assert_eq!(*message, Message::read(&mut input, *encoding).unwrap());
+        }
+        let end = Message::read(&mut input, *encoding).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, end.kind());
+    }
+
+    assert_eq!(r#"{"type":"command","command":"left"}"#, messages[5].json(VERSION));
+    assert_eq!(vec![6, 3], messages[5].encode(VERSION));
+
+    match messages[4] {
+        Message::Tick(ref events) => assert!(events.iter().any(|e| matches!(*e, Event::Hazard { .. }))),
+        _ => unreachable!(),
+    }
+
+    // hello as version 1 puts it
+    let hello = Message::Hello { min_version: 1, max_version: 1, board: None, watch: false };
+    let body = hello.encode(1);
+    assert_eq!(hello.encode(VERSION).len(), body.len() + 1);
+    assert_eq!(Some(hello), Message::decode(&body));
+
+    // a snapshot from before the hazards
+    let snapshot = Message::Snapshot(Game::with_seed(Board{x: 8, y: 6}, 3, Topology::WALLS, 1).snapshot());
+    let body = snapshot.encode(4);
+    assert_eq!(snapshot.encode(VERSION).len(), body.len() + 2);
+    assert_eq!(Some(snapshot.clone()), Message::decode(&body));
+    assert!(!snapshot.json(4).contains("hazards"));
+    assert_eq!(Some(snapshot.clone()), Message::parse_json(&snapshot.json(3)));
+
+    assert_eq!(MIN_VERSION, oldest_version(Topology::TORUS, Tiling::Square, false));
+    assert_eq!(3, oldest_version(Topology { x: Edge::Wrap, y: Edge::Twist }, Tiling::Square, false));
+    assert_eq!(4, oldest_version(Topology::WALLS, Tiling::Hex, false));
+    assert_eq!(5, oldest_version(Topology::WALLS, Tiling::Square, true));
+
+    assert_eq!(Some(1), negotiate(0, 1));
+    assert_eq!(Some(VERSION), negotiate(1, 200));
+    assert_eq!(None, negotiate(VERSION + 1, VERSION + 2));
+}
+
+// Broken messages are turned down, whatever they hold: they can't make
+// the decoders, nor the games the messages are applied to, panic.
+#[test]
+fn wire_fuzz_test() {
+    use self::rand::{Rng, SeedableRng};
+    use self::rand::rngs::StdRng;
+    use game::Game;
+
+    let mut rng = StdRng::seed_from_u64(43);
+    let messages = sample_messages();
+    let mut game = Game::with_players(Board{x: 16, y: 12}, 4, Topology::TORUS, 5, 2).with_hazards(3);
+
+    let mut try_message = |message: Option<Message>| {
+        match message {
+            Some(Message::Snapshot(snapshot)) => {
+                if let Some(mut copy) = Game::from_snapshot(&snapshot) {
+                    copy.exec_all(&[Command::Nop, Command::Nop]);
+                }
+            }
+            Some(Message::Tick(events)) => {
+                game.apply(&events);
+            }
+            _ => (),
+        }
+    };
+
+    for i in 0 .. 20000 {
+        let message = &messages[i % messages.len()];
+
+        let mut body = message.encode(VERSION);
+        let mut json = message.json(VERSION).into_bytes();
+        for data in &mut [&mut body, &mut json] {
+            match rng.gen_range(0, 4) {
+                0 => {
+                    let len = rng.gen_range(0, data.len() + 1);
+                    data.truncate(len);
+                }
+                1 => {
+                    let at = rng.gen_range(0, data.len());
+                    data[at] = rng.gen();
+                }
+                2 => {
+                    let at = rng.gen_range(0, data.len() + 1);
+                    data.insert(at, rng.gen());
+                }
+                _ => {
+                    for x in data.iter_mut() {
+                        if rng.gen_range(0, 8) == 0 {
+                            *x = rng.gen();
+                        }
+                    }
+                }
+            }
+        }
+
+        try_message(Message::decode(&body));
+        try_message(String::from_utf8(json).ok().and_then(|s| Message::parse_json(&s)));
+
+        // and the framing
+        let mut frame = (rng.gen::<u32>() >> rng.gen_range(0, 32)).to_be_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        let _ = Message::read(&mut &frame[..], Encoding::Binary);
+    }
+}
@@ -0,0 +1,567 @@
+// Game server: plays a game for several players over TCP
+//
+// Waits for all the players to connect, then plays the game on a fixed
+// clock: each tick goes with the last command every player sent since
+// the one before, and everyone gets the events of the tick. A player
+// whose connection drops, or who can't keep up with the ticks, is out.
+// Those who come to watch get the same as the players, whenever they
+// come.
+
+extern crate snake_engine;
+
+use std::{env, fs, process, thread};
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use snake_engine::utypes::{Board, parse_board};
+use snake_engine::game::{Command, Game};
+use snake_engine::topology::Topology;
+use snake_engine::tiling::Tiling;
+use snake_engine::wire::{DEFAULT_ADDR, MIN_VERSION, VERSION, Encoding, Message, Welcome, negotiate,
+                         oldest_version};
+
+const USAGE: &str = "usage: server [--listen ADDR] [--players N] [--board WxH] [--walls] \
+                     [--world NAME] [--hex] [--hazards N] [--delay MS] [--seed S] \
+                     [--record FILE]";
+
+// unless the server or the first player asks for another one
+const DEFAULT_BOARD: Board = Board{x: 24, y: 16};
+// the most a player may ask for
+const MAX_BOARD: Board = Board{x: 256, y: 256};
+
+// how long a client may take to say hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// how long the game waits on anyone slow to take the news
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+struct Options {
+    listen: String,
+    players: usize,
+    board: Option<Board>,
+    topology: Topology,
+    tiling: Tiling,
+    hazards: usize,
+    delay: Duration,
+    seed: Option<u64>,
+    record: Option<PathBuf>,    // where the replay goes
+}
+
+// a player's connection, and how they speak
+struct Client {
+    stream: TcpStream,
+    encoding: Encoding,
+    version: u8,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut opts = Options {
+        listen: DEFAULT_ADDR.to_string(),
+        players: 2,
+        board: None,
+        topology: Topology::TORUS,
+        tiling: Tiling::Square,
+        hazards: 0,
+        delay: Duration::from_millis(200),
+        seed: None,
+        record: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--listen" => opts.listen = value(),
+            "--players" => opts.players = value().parse().unwrap_or_else(|_| usage()),
+            "--board" => opts.board = Some(parse_board(&value()).unwrap_or_else(|| usage())),
+            "--walls" => opts.topology = Topology::WALLS,
+            "--world" => opts.topology = Topology::by_name(&value()).unwrap_or_else(|| usage()),
+            "--hex" => opts.tiling = Tiling::Hex,
+            "--hazards" => opts.hazards = value().parse().unwrap_or_else(|_| usage()),
+            "--delay" => opts.delay = Duration::from_millis(value().parse().unwrap_or_else(|_| usage())),
+            "--seed" => opts.seed = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--record" => opts.record = Some(PathBuf::from(value())),
+            _ => usage(),
+        }
+    }
+
+    let board = opts.board.unwrap_or(DEFAULT_BOARD);
+    if opts.players == 0 || opts.players > board.y as usize {
+        eprintln!("between 1 and {} players fit on the board", board.y);
+        process::exit(1);
+    }
+    if !opts.topology.fits(board, opts.tiling) {
+        eprintln!("hexagons don't go with the {} world on {} rows", opts.topology.name(), board.y);
+        process::exit(1);
+    }
+
+    opts
+}
+
+// someone who said hello
+struct Joiner {
+    addr: SocketAddr,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,   // may have read ahead of the stream
+    encoding: Encoding,
+    version: u8,
+    board: Option<Board>,           // the one they'd like
+    watch: bool,
+}
+
+// The client's hello, answered with a reject if there is no version both
+// speak, `oldest` at the least.
+fn handshake(stream: TcpStream, addr: SocketAddr, oldest: u8) -> io::Result<Joiner> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let encoding = Encoding::detect(&mut reader)?;
+
+    let (min_version, max_version, board, watch) = match Message::read(&mut reader, encoding)? {
+        Message::Hello { min_version, max_version, board, watch } => {
+            (min_version, max_version, board, watch)
+        }
+        other => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("hello expected, got {:?}", other)));
+        }
+    };
+    let version = match negotiate(min_version.max(oldest), max_version) {
+        Some(version) => version,
+        None => {
+            let why = format!("the server speaks versions {} to {}, not {} to {}",
+                              oldest.max(MIN_VERSION), VERSION, min_version, max_version);
+            // put the same way in every version
+            Message::Reject(why.clone()).write(&mut &stream, encoding, MIN_VERSION)?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, why));
+        }
+    };
+
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    Ok(Joiner { addr, stream, reader, encoding, version, board, watch })
+}
+
+// Says hello to whoever connects, for as long as the server is up, and
+// passes them on: those speaking `oldest` or a later version. Each one
+// gets a thread of their own, so that one slow to say hello doesn't
+// keep the others waiting.
+fn accept_all(listener: TcpListener, oldest: u8, tx: mpsc::Sender<Joiner>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let addr = match stream.peer_addr() {
+            Ok(addr) => addr,
+            Err(_) => continue,
+        };
+        let tx = tx.clone();
+        thread::spawn(move || match handshake(stream, addr, oldest) {
+            // once the game is over there is no one to pass them to
+            Ok(joiner) => { let _ = tx.send(joiner); }
+            Err(e) => eprintln!("{} turned away: {}", addr, e),
+        });
+    }
+}
+
+// Passes on the commands of a player until they disconnect (or say
+// something that makes no sense), which is as good as quitting.
+fn read_commands(player: usize, mut reader: BufReader<TcpStream>, encoding: Encoding,
+                 tx: mpsc::Sender<(usize, Command)>) {
+    loop {
+        match Message::read(&mut reader, encoding) {
+            Ok(Message::Command(cmd)) => {
+                if tx.send((player, cmd)).is_err() {
+                    return;
+                }
+            }
+            Ok(other) => eprintln!("player {}: command expected, got {:?}", player, other),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => {
+                eprintln!("player {}: {}", player, e);
+                break;
+            }
+        }
+    }
+    let _ = tx.send((player, Command::Exit));
+}
+
+// Sends the message to everyone still connected, letting go of those it
+// can't get to in time (a player's reader then quits for them).
+fn broadcast(clients: &mut [Option<Client>], message: &Message) {
+    for client in clients.iter_mut() {
+        let sent = client.as_mut().map(|c| message.write(&mut c.stream, c.encoding, c.version));
+        if let Some(Err(_)) = sent {
+            if let Some(c) = client.take() {
+                let _ = c.stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+}
+
+// adds the message to the replay, if there is one and it still works
+fn keep(record: &mut Option<&mut dyn Write>, message: &Message) {
+    let written = record.as_mut().map(|out| message.write(*out, Encoding::Binary, VERSION));
+    if let Some(Err(e)) = written {
+        eprintln!("could not record the game: {}", e);
+        *record = None;
+    }
+}
+
+// the board a player may ask for, with room for everyone and that the
+// world fits
+fn fits(board: Board, opts: &Options) -> bool {
+    board.x > 4 && board.y > 4 && board.x <= MAX_BOARD.x && board.y <= MAX_BOARD.y
+        && opts.players <= board.y as usize && opts.topology.fits(board, opts.tiling)
+}
+
+// Tells the one who came to watch what the game is like and how it is.
+// The game goes on without a watcher who can't keep up.
+fn welcome_watcher(joiner: Joiner, welcome: &Welcome, snapshot: &Message) -> Option<Client> {
+    eprintln!("{} came to watch", joiner.addr);
+    let mut watcher = Some(Client {
+        stream: joiner.stream, encoding: joiner.encoding, version: joiner.version,
+    });
+    let welcome = Welcome { version: joiner.version, ..welcome.clone() };
+    broadcast(std::slice::from_mut(&mut watcher), &Message::Welcome(welcome));
+    broadcast(std::slice::from_mut(&mut watcher), snapshot);
+    watcher
+}
+
+// Waits for the players and plays the game through, the replay going to
+// `record`. Returns the game as it ended along with how long each snake
+// got.
+fn serve(listener: &TcpListener, opts: &Options, seed: u64, mut record: Option<&mut dyn Write>)
+    -> io::Result<(Game, Vec<usize>)>
+{
+    let (joiners_tx, joiners) = mpsc::channel();
+    let listener = listener.try_clone()?;
+    // those whose version can't tell of the game are turned away
+    let oldest = oldest_version(opts.topology, opts.tiling, opts.hazards > 0);
+    thread::spawn(move || accept_all(listener, oldest, joiners_tx));
+
+    let (tx, rx) = mpsc::channel();
+    let mut clients = Vec::with_capacity(opts.players);
+    let mut waiting = Vec::new();   // to watch, once the game starts
+    let mut board = opts.board;
+    while clients.len() < opts.players {
+        let joiner: Joiner = joiners.recv().unwrap();
+        if joiner.watch {
+            waiting.push(joiner);
+            continue;
+        }
+
+        // the first player gets to choose, if the server didn't
+        if board.is_none() {
+            board = Some(joiner.board.filter(|&b| fits(b, opts)).unwrap_or(DEFAULT_BOARD));
+        }
+
+        let player = clients.len();
+        eprintln!("player {} joined from {} ({} of {})",
+                  player, joiner.addr, player + 1, opts.players);
+
+        let tx = tx.clone();
+        let (reader, encoding) = (joiner.reader, joiner.encoding);
+        thread::spawn(move || read_commands(player, reader, encoding, tx));
+        clients.push(Some(Client { stream: joiner.stream, encoding, version: joiner.version }));
+    }
+
+    let board = board.unwrap_or(DEFAULT_BOARD);
+    let mut game = Game::with_players(board, 4, opts.topology, seed, opts.players)
+        .with_tiling(opts.tiling)
+        .with_hazards(opts.hazards);
+    let welcome = Welcome {
+        version: VERSION,
+        player: None,
+        players: opts.players,
+        board,
+        topology: opts.topology,
+        tiling: opts.tiling,
+        tick_ms: opts.delay.as_millis() as u32,
+    };
+    for (player, client) in clients.iter_mut().enumerate() {
+        let version = client.as_ref().map_or(VERSION, |c| c.version);
+        let welcome = Welcome { version, player: Some(player), ..welcome.clone() };
+        broadcast(std::slice::from_mut(client), &Message::Welcome(welcome));
+    }
+    let snapshot = Message::Snapshot(game.snapshot());
+    broadcast(&mut clients, &snapshot);
+    for joiner in waiting {
+        clients.push(welcome_watcher(joiner, &welcome, &snapshot));
+    }
+
+    keep(&mut record, &Message::Welcome(welcome.clone()));
+    keep(&mut record, &snapshot);
+
+    let mut lengths: Vec<usize> = game.snakes().iter().map(|s| s.len()).collect();
+    let mut next_tick = Instant::now() + opts.delay;
+    loop {
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        }
+        next_tick += opts.delay;
+
+        // too late to play, not to watch
+        while let Ok(mut joiner) = joiners.try_recv() {
+            if joiner.watch {
+                let snapshot = Message::Snapshot(game.snapshot());
+                clients.push(welcome_watcher(joiner, &welcome, &snapshot));
+            } else {
+                eprintln!("{} came too late to play", joiner.addr);
+                let why = Message::Reject("the game is on, come to watch it".to_string());
+                let _ = why.write(&mut joiner.stream, joiner.encoding, joiner.version);
+            }
+        }
+
+        let mut commands = vec![Command::Nop; opts.players];
+        while let Ok((player, cmd)) = rx.try_recv() {
+            // quitting can't be taken back
+            if commands[player] != Command::Exit {
+                commands[player] = cmd;
+            }
+        }
+
+        let on = game.exec_all(&commands).is_some();
+        for snake in game.snakes() {
+            lengths[snake.player] = snake.len();
+        }
+        let tick = Message::Tick(game.events().to_vec());
+        broadcast(&mut clients, &tick);
+        keep(&mut record, &tick);
+
+        if !on {
+            // the readers let go of theirs too
+            for client in clients.iter().flatten() {
+                let _ = client.stream.shutdown(Shutdown::Both);
+            }
+            if let Some(out) = record {
+                out.flush()?;
+            }
+            return Ok((game, lengths));
+        }
+    }
+}
+
+fn main() {
+    let opts = parse_options();
+    let seed = opts.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_nanos() as u64)
+    });
+
+    let listener = TcpListener::bind(&opts.listen).unwrap_or_else(|e| {
+        eprintln!("can't listen on {}: {}", opts.listen, e);
+        process::exit(1);
+    });
+    eprintln!("waiting for {} players on {}, seed {}", opts.players, opts.listen, seed);
+
+    let mut record = opts.record.as_ref().map(|path| {
+        BufWriter::new(fs::File::create(path).unwrap_or_else(|e| {
+            eprintln!("can't write {}: {}", path.display(), e);
+            process::exit(1);
+        }))
+    });
+
+    match serve(&listener, &opts, seed, record.as_mut().map(|r| r as &mut dyn Write)) {
+        Ok((game, lengths)) => {
+            for (player, length) in lengths.iter().enumerate() {
+                let result = match game.player_snake(player) {
+                    Some(_) if game.players() > 1 => "last one standing".to_string(),
+                    _ => format!("{:?}", game.out(player).unwrap()),
+                };
+                println!("player {}: length {}, {}", player, length, result);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[test]
+fn server_localhost_test() {
+    use std::io::BufRead;
+    use snake_engine::game::{Dir, GameOver};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let opts = Options {
+        listen: addr.to_string(),
+        players: 2,
+        board: None,
+        topology: Topology::TORUS,
+        tiling: Tiling::Square,
+        hazards: 0,
+        delay: Duration::from_millis(20),
+        seed: None,
+        record: None,
+    };
+    let hello = |min_version, max_version| Message::Hello {
+        min_version, max_version, board: Some(Board{x: 12, y: 8}), watch: false,
+    };
+
+    // no version in common, no game
+    let mut stream = TcpStream::connect(addr).unwrap();
+    hello(VERSION + 1, VERSION + 1).write(&mut stream, Encoding::Binary, VERSION).unwrap();
+    let rejected = thread::spawn(move || {
+        Message::read(&mut BufReader::new(stream), Encoding::Binary).unwrap()
+    });
+
+    // someone watches the whole game, never saying a word
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let watch = Message::Hello { min_version: VERSION, max_version: VERSION, board: None, watch: true };
+    watch.write(&mut stream, Encoding::Json, VERSION).unwrap();
+    let watcher = thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut read = || Message::read(&mut reader, Encoding::Json);
+        match read().unwrap() {
+            Message::Welcome(welcome) => assert_eq!(None, welcome.player),
+            other => panic!("{:?}", other),
+        }
+        let mut game = match read().unwrap() {
+            Message::Snapshot(snapshot) => Game::from_snapshot(&snapshot).unwrap(),
+            other => panic!("{:?}", other),
+        };
+        loop {
+            match read() {
+                Ok(Message::Tick(events)) => game.apply(&events),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return game,
+                other => panic!("{:?}", other),
+            };
+        }
+    });
+
+    // The players play their copies along, one speaking binary, the other
+    // JSON. The first one quits at once, the second one a few ticks later.
+    let clients: Vec<_> = [Encoding::Binary, Encoding::Json].iter().map(|&encoding| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        hello(MIN_VERSION, VERSION).write(&mut stream, encoding, VERSION).unwrap();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut read = || Message::read(&mut reader, encoding);
+            let welcome = match read().unwrap() {
+                Message::Welcome(welcome) => welcome,
+                other => panic!("{:?}", other),
+            };
+            assert_eq!(Board{x: 12, y: 8}, welcome.board);
+            let mut game = match read().unwrap() {
+                Message::Snapshot(snapshot) => Game::from_snapshot(&snapshot).unwrap(),
+                other => panic!("{:?}", other),
+            };
+
+            let first = welcome.player == Some(0);
+            let cmd = if first { Command::Exit } else { Command::Move(Dir::Up) };
+            Message::Command(cmd).write(&mut stream, encoding, welcome.version).unwrap();
+
+            for tick in 0 .. {
+                match read() {
+                    Ok(Message::Tick(events)) => game.apply(&events),
+                    Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    other => panic!("{:?}", other),
+                };
+                if tick == 3 {
+                    Message::Command(Command::Exit).write(&mut stream, encoding, welcome.version).unwrap();
+                }
+            }
+            game
+        })
+    }).collect();
+
+    let mut replay = Vec::new();
+    let (game, lengths) = serve(&listener, &opts, 7, Some(&mut replay)).unwrap();
+    drop(listener);
+    assert_eq!(Some(GameOver::Exit), game.out(0));
+    assert_eq!(Some(GameOver::Exit), game.out(1));
+    assert!(lengths.iter().all(|&len| len >= 4));
+
+    let cells = |game: &Game| game.snake().into_iter().collect::<Vec<_>>();
+    for client in clients.into_iter().chain(Some(watcher)) {
+        let copy = client.join().unwrap();
+        assert_eq!(game.over(), copy.over());
+        assert_eq!(cells(&game), cells(&copy));
+    }
+    assert!(matches!(rejected.join().unwrap(), Message::Reject(_)));
+
+    // the replay makes the same game once more
+    let mut input = &replay[..];
+    match Message::read(&mut input, Encoding::Binary).unwrap() {
+        Message::Welcome(welcome) => assert_eq!(None, welcome.player),
+        other => panic!("{:?}", other),
+    }
+    let mut copy = match Message::read(&mut input, Encoding::Binary).unwrap() {
+        Message::Snapshot(snapshot) => Game::from_snapshot(&snapshot).unwrap(),
+        other => panic!("{:?}", other),
+    };
+    while !input.fill_buf().unwrap().is_empty() {
+        match Message::read(&mut input, Encoding::Binary).unwrap() {
+            Message::Tick(events) => copy.apply(&events),
+            other => panic!("{:?}", other),
+        };
+    }
+    assert_eq!(game.over(), copy.over());
+    assert_eq!(cells(&game), cells(&copy));
+}
+
+// Those whose version can't tell of the game are turned away, the others
+// get it put the way their version puts it.
+#[test]
+fn server_versions_test() {
+    use std::io::Read;
+
+    for &(tiling, hazards) in &[(Tiling::Hex, 0), (Tiling::Square, 2)] {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let opts = Options {
+            listen: addr.to_string(),
+            players: 1,
+            board: None,
+            topology: Topology::WALLS,
+            tiling,
+            hazards,
+            delay: Duration::from_millis(20),
+            seed: None,
+            record: None,
+        };
+        let oldest = oldest_version(opts.topology, tiling, hazards > 0);
+
+        // one speaking up to a version too old, one up to the oldest that will do
+        let clients: Vec<_> = [oldest - 1, oldest].iter().map(|&max_version| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let hello = Message::Hello { min_version: MIN_VERSION, max_version, board: None, watch: false };
+            hello.write(&mut stream, Encoding::Binary, max_version).unwrap();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                match Message::read(&mut reader, Encoding::Binary).unwrap() {
+                    Message::Welcome(welcome) => assert_eq!(max_version, welcome.version),
+                    Message::Reject(_) => return false,
+                    other => panic!("{:?}", other),
+                }
+
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len).unwrap();
+                let mut body = vec![0; u32::from_be_bytes(len) as usize];
+                reader.read_exact(&mut body).unwrap();
+                let snapshot = Message::decode(&body).unwrap();
+                assert_eq!(snapshot.encode(max_version), body);
+
+                Message::Command(Command::Exit).write(&mut stream, Encoding::Binary, max_version).unwrap();
+                while Message::read(&mut reader, Encoding::Binary).is_ok() {}
+                true
+            })
+        }).collect();
+
+        serve(&listener, &opts, 7, None).unwrap();
+        let played: Vec<bool> = clients.into_iter().map(|c| c.join().unwrap()).collect();
+        assert_eq!(vec![false, true], played);
+    }
+}
@@ -0,0 +1,319 @@
+// Headless simulator: plays bots against fixed seeds at full speed,
+// no terminal involved, and prints how they did. Each bot plays the game
+// of a seed on its own, the longest snake winning, or with `--versus`
+// all of them play it together, a snake each, the last one standing
+// winning. External bots start afresh for every game.
+
+extern crate snake_engine;
+
+use std::{env, fs, process};
+use std::io::Write;
+use std::time::Duration;
+
+use snake_engine::utypes::{Board, parse_board};
+use snake_engine::game::{Command, Game, GameOver};
+use snake_engine::topology::Topology;
+use snake_engine::tiling::Tiling;
+use snake_engine::control::Controller;
+use snake_engine::bots;
+use snake_engine::external;
+use snake_engine::external::External;
+
+const USAGE: &str = "usage: sim [--bots NAME,...] [--games N] [--seed S] [--board WxH] \
+                     [--walls] [--world NAME] [--hex] [--hazards N] [--max-ticks N] \
+                     [--versus] [--csv FILE] [--external COMMAND]... [--move-ms MS] \
+                     [--log FILE]";
+
+struct Options {
+    bots: Vec<String>,
+    games: u64,
+    seed: u64,
+    board: Board,
+    topology: Topology,
+    tiling: Tiling,
+    hazards: usize,
+    max_ticks: Option<u64>,
+    versus: bool,               // all the bots in the same game
+    csv: Option<String>,
+    externals: Vec<String>,     // the commands starting them
+    limit: Duration,            // for every move of theirs
+    log: Option<String>,        // where all of them say goes
+}
+
+#[derive(Default)]
+struct Stats {
+    name: String,
+    games: u64,
+    wins: u64,
+    ties: u64,                  // shared the win with others
+    total_len: u64,
+    total_ticks: u64,
+    hit_wall: u64,
+    hit_self: u64,
+    hit_snake: u64,
+    hit_hazard: u64,
+    board_full: u64,
+    survived: u64,              // until the last tick there was
+    disqualified: u64,
+    external: bool,
+}
+
+impl Stats {
+    fn win_rate(&self) -> f64 { self.wins as f64 / self.games as f64 }
+    fn avg_len(&self) -> f64 { self.total_len as f64 / self.games as f64 }
+    fn avg_ticks(&self) -> f64 { self.total_ticks as f64 / self.games as f64 }
+
+    fn add(&mut self, outcome: &Outcome) {
+        self.games += 1;
+        self.total_len += outcome.length as u64;
+        self.total_ticks += outcome.ticks;
+        match outcome.out {
+            Some(GameOver::HitWall) => self.hit_wall += 1,
+            Some(GameOver::HitSelf) => self.hit_self += 1,
+            Some(GameOver::HitSnake) => self.hit_snake += 1,
+            Some(GameOver::HitHazard) => self.hit_hazard += 1,
+            Some(GameOver::BoardFull) => self.board_full += 1,
+            // only a disqualified bot exits
+            Some(GameOver::Exit) => self.disqualified += 1,
+            None => self.survived += 1,
+        }
+    }
+}
+
+// how a bot did in a game
+struct Outcome {
+    length: usize,
+    ticks: u64,                 // until it was out
+    out: Option<GameOver>,      // `None` if it lasted the whole game
+    standing: bool,             // still on the board at the end
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut opts = Options {
+        bots: bots::NAMES.iter().map(|s| s.to_string()).collect(),
+        games: 100,
+        seed: 0,
+        board: Board{x: 16, y: 16},
+        topology: Topology::TORUS,
+        tiling: Tiling::Square,
+        hazards: 0,
+        max_ticks: None,
+        versus: false,
+        csv: None,
+        externals: Vec::new(),
+        limit: external::DEFAULT_LIMIT,
+        log: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--bots" => opts.bots = value().split(',').map(|s| s.to_string()).collect(),
+            "--games" => opts.games = value().parse().unwrap_or_else(|_| usage()),
+            "--seed" => opts.seed = value().parse().unwrap_or_else(|_| usage()),
+            "--board" => opts.board = parse_board(&value()).unwrap_or_else(|| usage()),
+            "--walls" => opts.topology = Topology::WALLS,
+            "--world" => opts.topology = Topology::by_name(&value()).unwrap_or_else(|| usage()),
+            "--hex" => opts.tiling = Tiling::Hex,
+            "--hazards" => opts.hazards = value().parse().unwrap_or_else(|_| usage()),
+            "--max-ticks" => opts.max_ticks = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--versus" => opts.versus = true,
+            "--csv" => opts.csv = Some(value()),
+            "--external" => opts.externals.push(value()),
+            "--move-ms" => opts.limit = Duration::from_millis(value().parse().unwrap_or_else(|_| usage())),
+            "--log" => opts.log = Some(value()),
+            _ => usage(),
+        }
+    }
+
+    if !opts.topology.fits(opts.board, opts.tiling) {
+        eprintln!("hexagons don't go with the {} world on {} rows",
+                  opts.topology.name(), opts.board.y);
+        process::exit(1);
+    }
+
+    for name in &opts.bots {
+        if bots::by_name(name, 0).is_none() {
+            eprintln!("unknown bot '{}', known bots: {}", name, bots::NAMES.join(", "));
+            process::exit(1);
+        }
+    }
+
+    let players = opts.bots.len() + opts.externals.len();
+    if opts.versus && players > opts.board.y as usize {
+        eprintln!("at most {} bots fit on the board", opts.board.y);
+        process::exit(1);
+    }
+
+    opts
+}
+
+// the game of a seed for that many players
+fn new_game(opts: &Options, seed: u64, players: usize) -> Game {
+    Game::with_players(opts.board, 4, opts.topology, seed, players)
+        .with_tiling(opts.tiling)
+        .with_hazards(opts.hazards)
+}
+
+// plays the game of a seed out with the bot on its own
+fn play_solo(bot: &mut dyn Controller, opts: &Options, seed: u64, max_ticks: u64) -> Outcome {
+    let mut game = new_game(opts, seed, 1);
+    let mut ticks = max_ticks;
+    for tick in 1 ..= max_ticks {
+        let cmd = bot.command(&game);
+        if game.exec(cmd).is_none() {
+            ticks = tick;
+            break;
+        }
+    }
+    Outcome { length: game.snake().len(), ticks, out: game.over(), standing: true }
+}
+
+// Plays the game of a seed out with all the bots at once, the bot `i`
+// with the snake of `seats[i]`; how each of them did, in the same order.
+fn play_versus(bots: &mut [Box<dyn Controller>], seats: &[usize], opts: &Options, seed: u64,
+               max_ticks: u64) -> Vec<Outcome> {
+    let mut game = new_game(opts, seed, bots.len());
+    let mut outcomes: Vec<Outcome> = seats.iter().map(|&player| Outcome {
+        length: game.player_snake(player).map_or(0, |s| s.len()),
+        ticks: max_ticks,
+        out: None,
+        standing: true,
+    }).collect();
+
+    let mut commands = vec![Command::Nop; bots.len()];
+    for tick in 1 ..= max_ticks {
+        for (bot, &player) in bots.iter_mut().zip(seats) {
+            // those out have nothing more to say
+            commands[player] = match game.player_snake(player) {
+                Some(_) => bot.command(&game),
+                None => Command::Nop,
+            };
+        }
+        let on = game.exec_all(&commands).is_some();
+
+        for (outcome, &player) in outcomes.iter_mut().zip(seats) {
+            if let Some(snake) = game.player_snake(player) {
+                outcome.length = snake.len();
+            }
+            if outcome.out.is_none() && game.out(player).is_some() {
+                outcome.out = game.out(player);
+                outcome.ticks = tick;
+            }
+        }
+        if !on {
+            break;
+        }
+    }
+
+    for (outcome, &player) in outcomes.iter_mut().zip(seats) {
+        outcome.standing = game.player_snake(player).is_some();
+    }
+    outcomes
+}
+
+// the bot to play a game for `player`, a new one every time
+fn start_bot(s: &Stats, opts: &Options, player: usize, log: Option<&fs::File>)
+    -> Box<dyn Controller>
+{
+    if !s.external {
+        return bots::by_name(&s.name, player).unwrap();
+    }
+
+    let mut bot = External::spawn(&s.name, player, opts.limit).unwrap_or_else(|e| {
+        eprintln!("can't start {}: {}", s.name, e);
+        process::exit(1);
+    });
+    if let Some(log) = log.and_then(|log| log.try_clone().ok()) {
+        bot.log_to(Box::new(log));
+    }
+    Box::new(bot)
+}
+
+fn print_table(stats: &[Stats]) {
+    println!("{:<12} {:>6} {:>6} {:>6} {:>8} {:>8} {:>10} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9} {:>6}",
+             "bot", "games", "wins", "ties", "win%", "avg len", "avg ticks",
+             "wall", "self", "snake", "hazard", "full", "survived", "disq");
+    for s in stats {
+        println!("{:<12} {:>6} {:>6} {:>6} {:>7.1}% {:>8.1} {:>10.1} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9} {:>6}",
+                 s.name, s.games, s.wins, s.ties, 100.0 * s.win_rate(), s.avg_len(), s.avg_ticks(),
+                 s.hit_wall, s.hit_self, s.hit_snake, s.hit_hazard, s.board_full, s.survived,
+                 s.disqualified);
+    }
+}
+
+fn write_csv(path: &str, stats: &[Stats]) -> std::io::Result<()> {
+    let mut f = fs::File::create(path)?;
+    writeln!(f, "bot,games,wins,ties,win_rate,avg_length,avg_ticks,hit_wall,hit_self,hit_snake,\
+                 hit_hazard,board_full,survived,disqualified")?;
+    for s in stats {
+        writeln!(f, "{},{},{},{},{:.4},{:.2},{:.2},{},{},{},{},{},{},{}",
+                 s.name, s.games, s.wins, s.ties, s.win_rate(), s.avg_len(), s.avg_ticks(),
+                 s.hit_wall, s.hit_self, s.hit_snake, s.hit_hazard, s.board_full, s.survived,
+                 s.disqualified)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let opts = parse_options();
+
+    // long enough for the Hamiltonian bot to fill the board
+    let area = opts.board.x as u64 * opts.board.y as u64;
+    let max_ticks = opts.max_ticks.unwrap_or(area * area);
+
+    let mut stats: Vec<Stats> = opts.bots.iter()
+        .map(|name| Stats { name: name.clone(), ..Default::default() })
+        .chain(opts.externals.iter().map(|command| {
+            Stats { name: command.clone(), external: true, ..Default::default() }
+        }))
+        .collect();
+
+    let log = opts.log.as_ref().map(|path| fs::File::create(path).unwrap_or_else(|e| {
+        eprintln!("can't write {}: {}", path, e);
+        process::exit(1);
+    }));
+
+    for seed in opts.seed .. opts.seed + opts.games {
+        let outcomes: Vec<Outcome> = if opts.versus {
+            // the snakes take turns at the starting rows, seed by seed
+            let n = stats.len();
+            let seats: Vec<usize> = (0 .. n).map(|i| (i + seed as usize) % n).collect();
+            let mut bots: Vec<Box<dyn Controller>> = stats.iter().zip(&seats)
+                .map(|(s, &player)| start_bot(s, &opts, player, log.as_ref()))
+                .collect();
+            play_versus(&mut bots, &seats, &opts, seed, max_ticks)
+        } else {
+            stats.iter().map(|s| {
+                let mut bot = start_bot(s, &opts, 0, log.as_ref());
+                play_solo(bot.as_mut(), &opts, seed, max_ticks)
+            }).collect()
+        };
+
+        // the longest of those left standing wins, unless several are
+        let best = outcomes.iter().filter(|o| o.standing).map(|o| o.length).max();
+        let is_best = |o: &Outcome| o.standing && Some(o.length) == best;
+        let tied = outcomes.iter().filter(|o| is_best(o)).count() > 1;
+        for (s, outcome) in stats.iter_mut().zip(&outcomes) {
+            s.add(outcome);
+            if is_best(outcome) {
+                if tied { s.ties += 1 } else { s.wins += 1 }
+            }
+        }
+    }
+
+    print_table(&stats);
+
+    if let Some(path) = opts.csv {
+        if let Err(e) = write_csv(&path, &stats) {
+            eprintln!("can't write {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
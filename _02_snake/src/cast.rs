@@ -0,0 +1,158 @@
+// Asciicast export: a recorded game as an asciinema v2 `.cast` file
+//
+// The recording is played again through a renderer that keeps the
+// terminal output of every tick instead of showing it, timestamped with
+// the tick interval.
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::{Game, GameUpdate};
+use snake_engine::render::Renderer;
+use snake_engine::json;
+use snake_engine::{Controller, Recording};
+
+use draw::{cursor_pos, write_diff};
+use frame::Frame;
+use scene::{Canvas, Style, draw_scene};
+use theme::Depth;
+use viewport::Viewport;
+
+// room for the message below the board
+const MIN_WIDTH: i16 = 24;
+
+pub struct CastRenderer {
+    style: Style,
+    depth: Depth,
+    delay: Duration,
+    size: Board,
+    ticks: u32,
+    front: Frame,
+    back: Frame,
+    events: Vec<(Duration, String)>,    // the output and when it comes
+}
+
+impl CastRenderer {
+    // the whole board with the border around it, and a line below
+    pub fn new(game: &Game, style: Style, depth: Depth, delay: Duration) -> CastRenderer {
+        let style = style.for_game(game);
+        let mut size = style.scale(game.board() + Board{x: 2, y: 0}) + Board{x: 0, y: 3};
+        size.x = size.x.max(MIN_WIDTH);
+        CastRenderer {
+            style,
+            depth,
+            delay,
+            size,
+            ticks: 0,
+            front: Frame::new(size),
+            back: Frame::new(size),
+            events: Vec::new(),
+        }
+    }
+
+    // draws the scene, with `message` below it, and keeps what changed
+    fn present(&mut self, game: &Game, prefix: &str, message: &str) {
+        self.back.clear();
+        let board_offset = self.style.scale(Pos{x: 1, y: 1});
+        draw_scene(&mut self.back, board_offset, game, &Viewport::full(game.board()), self.style);
+        self.back.print_at_pos(Pos{x: 0, y: self.size.y - 1}, message);
+
+        let mut out = prefix.as_bytes().to_vec();
+        write_diff(&mut out, &self.front, &self.back, self.depth).unwrap();
+        std::mem::swap(&mut self.front, &mut self.back);
+
+        let time = self.delay * self.ticks;
+        self.events.push((time, String::from_utf8(out).unwrap()));
+    }
+
+    // asciicast v2: a header line, then an `[time, "o", output]` line
+    // for each event
+    pub fn write(&self, out: &mut dyn Write, title: &str) -> io::Result<()> {
+        writeln!(out, "{{\"version\": 2, \"width\": {}, \"height\": {}, \"title\": {}}}",
+                 self.size.x, self.size.y, json_string(title))?;
+        for (time, output) in &self.events {
+            writeln!(out, "[{:.3}, \"o\", {}]", time.as_secs_f64(), json_string(output))?;
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for CastRenderer {
+    fn init(&mut self, game: &Game) {
+        let prefix = format!("{}{}", termion::clear::All, termion::cursor::Hide);
+        self.present(game, &prefix, "");
+    }
+
+    fn update(&mut self, game: &Game, _update: &GameUpdate) {
+        self.ticks += 1;
+        self.present(game, "", "");
+    }
+
+    fn fini(&mut self, game: &Game) {
+        self.ticks += 1;
+        let message = format!("Game over! Length {}", game.snake().len());
+        self.present(game, "", &message);
+
+        let last = self.events.last_mut().unwrap();
+        last.1 += &format!("{}{}", cursor_pos(Pos{x: 0, y: self.size.y}), termion::cursor::Show);
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::new();
+    json::write_string(&mut json, s).unwrap();
+    json
+}
+
+// plays the recording through and writes out the cast
+pub fn export(recording: &Recording, style: Style, depth: Depth, delay: Duration,
+              out: &mut dyn Write) -> io::Result<()> {
+    let mut game = recording.game();
+    let mut script = recording.script();
+    let mut renderer = CastRenderer::new(&game, style, depth, delay);
+
+    renderer.init(&game);
+    loop {
+        let cmd = script.command(&game);
+        match game.exec(cmd) {
+            Some(update) => renderer.update(&game, &update),
+            None => break,
+        }
+    }
+    renderer.fini(&game);
+
+    let title = format!("snake {}x{}, seed {}", recording.board.x, recording.board.y, recording.seed);
+    renderer.write(out, &title)
+}
+
+#[test]
+fn cast_simple_test() {
+    use snake_engine::{Command, Topology};
+    use snake_engine::game::Dir;
+
+    let mut recording = Recording::new(Board{x: 10, y: 8}, 4, Topology::TORUS, 1);
+    recording.push(Command::Move(Dir::Up));
+    recording.push(Command::Nop);
+
+    let mut out = Vec::new();
+    export(&recording, Style::default(), Depth::TrueColor, Duration::from_millis(250), &mut out)
+        .unwrap();
+    let cast = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = cast.lines().collect();
+
+    // the header, the start, two ticks and the game over after them
+    assert_eq!(5, lines.len());
+    assert_eq!(r#"{"version": 2, "width": 24, "height": 11, "title": "snake 10x8, seed 1"}"#,
+               lines[0]);
+    assert!(lines[1].starts_with(r#"[0.000, "o", "\u001b[2J"#));
+    assert!(lines[3].starts_with(r#"[0.500, "o", ""#));
+    assert!(lines[4].starts_with("[0.750, ") && lines[4].contains("Length"));
+}
+
+#[test]
+fn json_string_test() {
+    assert_eq!(r#""a\"b\\c\n\u001b[""#, json_string("a\"b\\c\n\x1b["));
+}
@@ -0,0 +1,220 @@
+// Client mode: playing a game that a server runs, or watching one
+//
+// The server has the game; the client keeps a copy of it in step with
+// the events the server sends for every tick, and sends the keys pressed
+// in return. The copy is drawn the way a game played here would be.
+// Watching is the same without the keys, and so is a replay, only with
+// the ticks read from a file.
+
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::mpsc::TryRecvError;
+use std::{thread, time};
+
+use snake_engine::{Command, Dir, Event, Game, Renderer};
+use snake_engine::utypes::Board;
+use snake_engine::wire::{Encoding, Message, Welcome, MIN_VERSION, VERSION};
+
+use draw::GameDrawer;
+use keyboard::Keyboard;
+use scene::Style;
+use theme::Depth;
+
+// how often the keyboard and the server are looked at
+const POLL_DELAY: time::Duration = time::Duration::from_millis(10);
+
+// the events of every tick, or why there are no more
+type Ticks = mpsc::Receiver<io::Result<Vec<Event>>>;
+
+fn unexpected(message: &Message) -> io::Error {
+    let text = match *message {
+        Message::Reject(ref why) => format!("the server turned us away: {}", why),
+        ref other => format!("unexpected message from the server: {}", other.json(VERSION)),
+    };
+    io::Error::new(io::ErrorKind::InvalidData, text)
+}
+
+// the welcome and the game as it is, which is how every game begins
+fn start(reader: &mut dyn BufRead, encoding: Encoding) -> io::Result<(Welcome, Game)> {
+    let welcome = match Message::read(reader, encoding)? {
+        Message::Welcome(welcome) => welcome,
+        other => return Err(unexpected(&other)),
+    };
+    let snapshot = match Message::read(reader, encoding)? {
+        Message::Snapshot(snapshot) => snapshot,
+        other => return Err(unexpected(&other)),
+    };
+    let game = Game::from_snapshot(&snapshot).ok_or_else(|| unexpected(&Message::Snapshot(snapshot)))?;
+    Ok((welcome, game))
+}
+
+// The ticks come through a thread of their own, not to hold up the keys,
+// `pace` apart if they are not to come as fast as they can be read. The
+// end of the input is an error called `gone`: the game is over before.
+fn pass_ticks<R>(mut reader: R, encoding: Encoding, pace: Option<time::Duration>, gone: &'static str)
+    -> Ticks
+    where R: BufRead + Send + 'static
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            let events = match Message::read(&mut reader, encoding) {
+                Ok(Message::Tick(events)) => Ok(events),
+                Ok(other) => Err(unexpected(&other)),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    Err(io::Error::new(io::ErrorKind::UnexpectedEof, gone))
+                }
+                Err(e) => Err(e),
+            };
+            if let Some(pace) = pace {
+                thread::sleep(pace);
+            }
+            let done = events.is_err();
+            if tx.send(events).is_err() || done {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+// the player after (or before) the one followed, among those still on
+fn next_player(game: &Game, player: usize, forward: bool) -> usize {
+    let players: Vec<usize> = game.snakes().iter().map(|s| s.player).collect();
+    let n = players.len();
+    match players.iter().position(|&p| p == player) {
+        Some(i) if forward => players[(i + 1) % n],
+        Some(i) => players[(i + n - 1) % n],
+        None => players.first().cloned().unwrap_or(player),
+    }
+}
+
+// Draws the game along with the ticks until it is over. The keys go to
+// `server` if there is one to play on (in the version spoken with it),
+// otherwise they quit or switch the snake the view follows.
+fn run(keyboard: &mut Keyboard, drawer: &mut GameDrawer, game: &mut Game, ticks: Ticks,
+       mut server: Option<(TcpStream, u8)>, mut player: usize) -> io::Result<()>
+{
+    // whatever was pressed while waiting
+    while keyboard.key().is_some() {}
+    drawer.follow(game, player);
+    drawer.init(game);
+
+    let mut result = Ok(());
+    'game: loop {
+        if let Some(size) = drawer.terminal_resized() {
+            drawer.resize(game, size);
+        }
+
+        if let Some((ref mut stream, version)) = server {
+            let cmd = keyboard.steer(game, player);
+            if cmd != Command::Nop {
+                if let Err(e) = Message::Command(cmd).write(stream, Encoding::Binary, version) {
+                    result = Err(e);
+                    break;
+                }
+                if cmd == Command::Exit {
+                    break;
+                }
+            }
+        } else {
+            while let Some(key) = keyboard.key() {
+                let forward = match keyboard.keymap().command(key, game.tiling()) {
+                    Command::Exit => break 'game,
+                    Command::Move(Dir::Right) | Command::Move(Dir::Down)
+                        | Command::Move(Dir::DownLeft) | Command::Move(Dir::DownRight) => true,
+                    Command::Move(_) => false,
+                    Command::Nop => continue,
+                };
+                player = next_player(game, player, forward);
+                drawer.follow(game, player);
+                drawer.present(game);
+            }
+        }
+
+        loop {
+            match ticks.try_recv() {
+                Ok(Ok(events)) => match game.apply(&events) {
+                    Some(update) => drawer.update(game, &update),
+                    None => break 'game,
+                },
+                Ok(Err(e)) => {
+                    result = Err(e);
+                    break 'game;
+                }
+                Err(TryRecvError::Empty) => break,
+                // only after an error, already taken
+                Err(TryRecvError::Disconnected) => break 'game,
+            }
+        }
+
+        thread::sleep(POLL_DELAY);
+    }
+
+    drawer.fini(game);
+    result
+}
+
+// connects to the server at `addr` with a hello
+fn connect(addr: &str, hello: Message) -> io::Result<(TcpStream, BufReader<TcpStream>)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    hello.write(&mut stream, Encoding::Binary, VERSION)?;
+    let reader = BufReader::new(stream.try_clone()?);
+    Ok((stream, reader))
+}
+
+// Plays on the server at `addr` until the game is over (or the player
+// quits), asking for a game on `board`. Returns the game as it ended and
+// which snake was theirs.
+pub fn play(keyboard: &mut Keyboard, addr: &str, board: Board, show_minimap: bool, style: Style,
+            depth: Depth) -> io::Result<(Game, usize)>
+{
+    let hello = Message::Hello { min_version: MIN_VERSION, max_version: VERSION, board: Some(board), watch: false };
+    let (stream, mut reader) = connect(addr, hello)?;
+    println!("Connected to {}, waiting for the other players...", addr);
+
+    let (welcome, mut game) = start(&mut reader, Encoding::Binary)?;
+    let player = welcome.player.unwrap_or(0);
+    let ticks = pass_ticks(reader, Encoding::Binary, None, "the server is gone");
+
+    let mut drawer = GameDrawer::new(&game, show_minimap, style, depth);
+    run(keyboard, &mut drawer, &mut game, ticks, Some((stream, welcome.version)), player)?;
+    Ok((game, player))
+}
+
+// Watches the game on the server at `addr` until it is over (or the
+// watcher had enough), the view following the snake of `player`.
+pub fn watch(keyboard: &mut Keyboard, addr: &str, player: usize, show_minimap: bool, style: Style,
+             depth: Depth) -> io::Result<Game>
+{
+    let hello = Message::Hello { min_version: MIN_VERSION, max_version: VERSION, board: None, watch: true };
+    let (_stream, mut reader) = connect(addr, hello)?;
+    println!("Connected to {}, waiting for the game to start...", addr);
+
+    let (_, mut game) = start(&mut reader, Encoding::Binary)?;
+    let ticks = pass_ticks(reader, Encoding::Binary, None, "the server is gone");
+
+    let mut drawer = GameDrawer::new(&game, show_minimap, style, depth);
+    run(keyboard, &mut drawer, &mut game, ticks, None, player)?;
+    Ok(game)
+}
+
+// Watches a game the server recorded, at the pace it was played.
+pub fn replay(keyboard: &mut Keyboard, path: &Path, player: usize, show_minimap: bool, style: Style,
+              depth: Depth) -> io::Result<Game>
+{
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let encoding = Encoding::detect(&mut reader)?;
+    let (welcome, mut game) = start(&mut reader, encoding)?;
+    let pace = time::Duration::from_millis(welcome.tick_ms as u64);
+    let ticks = pass_ticks(reader, encoding, Some(pace), "the replay ends before the game does");
+
+    let mut drawer = GameDrawer::new(&game, show_minimap, style, depth);
+    run(keyboard, &mut drawer, &mut game, ticks, None, player)?;
+    Ok(game)
+}
@@ -0,0 +1,174 @@
+// Config: the settings kept between the runs, as `key = value` lines
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use snake_engine::utypes::{Board, parse_board};
+use snake_engine::bots;
+use snake_engine::topology::Topology;
+use snake_engine::tiling::Tiling;
+
+use theme;
+use theme::Theme;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+    pub board: Board,
+    pub delay: u64,                 // milliseconds per move
+    pub topology: Topology,
+    pub tiling: Tiling,
+    pub hazards: usize,
+    pub theme: &'static Theme,
+    pub bot: Option<&'static str>,  // who plays, None for the player
+    pub practice: bool,             // the player may take moves back
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            board: Board{x: 16, y: 16},
+            delay: 500,
+            topology: Topology::TORUS,
+            tiling: Tiling::Square,
+            hazards: 0,
+            theme: theme::default(),
+            bot: None,
+            practice: false,
+        }
+    }
+}
+
+// where the config and the high scores live
+pub fn dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => Some(PathBuf::from(dir).join("snake")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("snake")),
+    }
+}
+
+impl Config {
+    // Missing keys and values that make no sense are left at the
+    // defaults, the unknown keys are skipped.
+    pub fn parse(text: &str) -> Config {
+        let mut config = Config::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut kv = line.splitn(2, '=').map(|s| s.trim());
+            let (key, value) = match (kv.next(), kv.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+
+            match key {
+                "board" => if let Some(board) = parse_board(value) {
+                    config.board = board;
+                },
+                "delay" => if let Ok(delay) = value.parse() {
+                    config.delay = delay;
+                },
+                "world" => if let Some(topology) = Topology::by_name(value) {
+                    config.topology = topology;
+                },
+                "grid" => if let Some(tiling) = Tiling::by_name(value) {
+                    config.tiling = tiling;
+                },
+                "hazards" => if let Ok(hazards) = value.parse() {
+                    config.hazards = hazards;
+                },
+                "theme" => if let Some(theme) = theme::by_name(value) {
+                    config.theme = theme;
+                },
+                "mode" => match value {
+                    "player" => config.bot = None,
+                    "practice" => config.practice = true,
+                    _ => if let Some(name) = bots::NAMES.iter().find(|&&n| n == value) {
+                        config.bot = Some(name);
+                    },
+                },
+                _ => (),
+            }
+        }
+        // like any setting that makes no sense, hexagons on a world they
+        // don't fit are left out
+        if !config.topology.fits(config.board, config.tiling) {
+            config.tiling = Tiling::Square;
+        }
+
+        config
+    }
+
+    pub fn text(&self) -> String {
+        format!("# snake settings\n\
+                 board = {}x{}\n\
+                 delay = {}\n\
+                 world = {}\n\
+                 grid = {}\n\
+                 hazards = {}\n\
+                 theme = {}\n\
+                 mode = {}\n",
+                self.board.x, self.board.y,
+                self.delay,
+                self.topology.name(),
+                self.tiling.name(),
+                self.hazards,
+                self.theme.name,
+                self.mode())
+    }
+
+    // who plays, the way the settings and the high scores put it
+    pub fn mode(&self) -> &'static str {
+        match self.bot {
+            Some(name) => name,
+            None if self.practice => "practice",
+            None => "player",
+        }
+    }
+
+    // the defaults if there is no config yet
+    pub fn load(path: &Path) -> io::Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Config::parse(&text)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.text())
+    }
+}
+
+#[test]
+fn config_simple_test() {
+    let config = Config {
+        board: Board{x: 40, y: 21},
+        delay: 150,
+        topology: Topology::by_name("mobius").unwrap(),
+        tiling: Tiling::Hex,
+        hazards: 3,
+        theme: theme::by_name("ascii").unwrap(),
+        bot: Some("pathfinder"),
+        practice: false,
+    };
+    assert_eq!(config, Config::parse(&config.text()));
+    let config = Config { bot: None, practice: true, ..config };
+    assert_eq!(config, Config::parse(&config.text()));
+
+    // junk is skipped, the rest is still read
+    let config = Config::parse("board = 2x2\nworld = walls\nwat\nmode = nobody\n");
+    assert_eq!(Config { topology: Topology::WALLS, ..Config::default() }, config);
+    // and so are hexagons where they don't fit
+    let config = Config::parse("world = klein\ngrid = hex\n");
+    assert_eq!(Tiling::Square, config.tiling);
+    // as the world used to be called
+    assert_eq!(Config::default(), Config::parse("world = periodic\n"));
+}
@@ -1,175 +1,396 @@
-// Game drawer
+// Game drawer: the termion renderer
 
 extern crate termion;
 
-use std::fmt;
+use std::io;
 use std::io::{Write, stdout};
 
 use termion::raw::IntoRawMode;
 
-use utypes::Pos;
-use utypes::Board;
-use game;
-use game::Game;
-use game::GameUpdate;
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::Game;
+use snake_engine::game::GameUpdate;
+use snake_engine::render::Renderer;
 
-const SYMBOL_EMPTY: &str = " ";
-const SYMBOL_BORDER: &str = "█";
-const SYMBOL_BORDER_PERIODIC: &str = "▒";
-const SYMBOL_SNAKE_BODY: &str = "o";
-const SYMBOL_SNAKE_HEAD: &str = "@";
-const SYMBOL_FOOD: &str = "¤";
+use frame::Frame;
+use menu::{Menu, draw_menu};
+use scene::{draw_minimap, draw_scene, minimap_size, Canvas, Style, WIDE_FILLER};
+use theme::{Depth, Tint};
+use viewport::Viewport;
+
+// the least of the board worth playing on
+const MIN_VIEW_SIZE: Board = Board{x: 10, y: 6};
 
 type Screen = termion::raw::RawTerminal<std::io::Stdout>;
 
 pub struct GameDrawer {
     screen: Screen,
-    board_offset: Pos,  // game board offset ( > base)
+    layout: Option<Layout>,     // None if the terminal is too small to play
+    view: Viewport,
+    player: usize,              // whose snake the view follows
+    show_minimap: bool,
+    style: Style,
+    depth: Depth,
+    terminal_size: Board,
+    front: Frame,               // what is on the screen
+    back: Frame,                // what is about to be
+    message: String,            // under the board
 }
 
-impl Pos {
-    fn into_cursor_pos(&self) -> termion::cursor::Goto {
-        termion::cursor::Goto((self.x + 1) as u16, (self.y + 1) as u16)
-    }
+// where things go on the screen
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Layout {
+    board_offset: Pos,      // top-left cell of the view
+    view_size: Board,       // the whole board, or as much of it as fits
+    minimap: Option<Pos>,   // top-left cell of the minimap
 }
 
-impl fmt::Display for game::Color {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use termion::color;
-        match self {
-            game::Color::White => color::Fg(color::White).fmt(f),
-            game::Color::Blue => color::Fg(color::Blue).fmt(f),
-            game::Color::Green => color::Fg(color::Green).fmt(f),
-            game::Color::Red => color::Fg(color::Red).fmt(f),
-        }
-    }
+pub fn cursor_pos(pos: Pos) -> termion::cursor::Goto {
+    termion::cursor::Goto((pos.x + 1) as u16, (pos.y + 1) as u16)
 }
 
 impl GameDrawer {
-    pub fn get_max_board_size() -> Board {
+    pub fn get_terminal_size() -> Board {
         let terminal_sizes = termion::terminal_size().unwrap();
-        let reserve = Board{x: 10, y: 10};
-        Board{x: terminal_sizes.0 as i16, y: terminal_sizes.1 as i16} - reserve
+        Board{x: terminal_sizes.0 as i16, y: terminal_sizes.1 as i16}
     }
 
-    pub fn new(game: &Game) -> GameDrawer {
-        let max_board_size = Self::get_max_board_size();
-
-        assert!(game.board.x <= max_board_size.x);
-        assert!(game.board.y <= max_board_size.y);
+    pub fn new(game: &Game, show_minimap: bool, style: Style, depth: Depth) -> GameDrawer {
+        let style = style.for_game(game);
+        let terminal_size = Self::get_terminal_size();
+        let layout = board_layout(game.board(), terminal_size, show_minimap, style);
+        let view_size = layout.map_or(game.board(), |l| l.view_size);
 
         GameDrawer {
             screen: stdout().into_raw_mode().unwrap(),
-            board_offset: Pos{x: 3, y: 3},
+            layout,
+            view: Viewport::around(game, game.snake().head(), view_size),
+            player: game.snake().player,
+            show_minimap,
+            style,
+            depth,
+            terminal_size,
+            front: Frame::new(terminal_size),
+            back: Frame::new(terminal_size),
+            message: String::new(),
         }
     }
 
-    pub fn init(&mut self, game: &Game) {
-        write!(self.screen, "{}{}",
-               termion::clear::All,
-               termion::cursor::Hide).unwrap();
-        self.draw_border(game);
-        self.draw_snake(game);
-        self.draw_food(game);
-        self.flush();
+    // a line of text under the board, until another one ("" for none)
+    pub fn say(&mut self, game: &Game, message: &str) {
+        self.message = message.to_string();
+        self.present(game);
     }
 
-    pub fn fini(&mut self, game: &Game) {
-        write!(self.screen, "{}\n\r{}{}\nGame over!\n\r\n",
-               (self.board_offset + game.board).into_cursor_pos(),
-               termion::style::Reset, termion::cursor::Show).unwrap();
+    // the terminal has no way to tell us it was resized, so ask it
+    pub fn terminal_resized(&self) -> Option<Board> {
+        let size = Self::get_terminal_size();
+        if size != self.terminal_size { Some(size) } else { None }
     }
 
-    pub fn flush(&mut self) {
-        self.screen.flush().unwrap();
+    // the view follows the snake of `player` (while it's on the board)
+    pub fn follow(&mut self, game: &Game, player: usize) {
+        self.player = player;
+        if let Some(layout) = self.layout {
+            self.view = Viewport::around(game, self.head(game), layout.view_size);
+        }
     }
 
-    pub fn update_scene(&mut self, game: &Game, update: &GameUpdate) {
-        self.set_color(game.snake.color);
+    fn head(&self, game: &Game) -> Pos {
+        game.player_snake(self.player).unwrap_or_else(|| game.snake()).head()
+    }
 
-        if let Some(pos) = update.head_prev_pos {
-            self.board_print_at_pos(pos, SYMBOL_SNAKE_BODY);
-        }
+    // the game has to wait until the terminal is enlarged
+    pub fn too_small(&self) -> bool {
+        self.layout.is_none()
+    }
 
-        if let Some(pos) = update.tail_prev_pos {
-            self.board_print_at_pos(pos, SYMBOL_EMPTY);
-        }
+    pub fn flush(&mut self) {
+        self.screen.flush().unwrap();
+    }
 
-        self.board_print_at_pos(game.snake.head(), SYMBOL_SNAKE_HEAD);
+    // draws the scene into the back frame and puts the difference on screen
+    pub fn present(&mut self, game: &Game) {
+        let layout = match self.layout {
+            Some(layout) => layout,
+            None => return,
+        };
 
-        self.reset_color();
+        let head = self.head(game);
+        self.view.follow(game, head);
 
-        if update.food_renew {
-            self.board_print_at_pos(game.food.pos, SYMBOL_FOOD);
+        self.back.clear();
+        draw_scene(&mut self.back, layout.board_offset, game, &self.view, self.style);
+        if let Some(offset) = layout.minimap {
+            draw_minimap(&mut self.back, offset, game, self.style);
+        }
+        if !self.message.is_empty() {
+            let y = layout.board_offset.y + self.style.scale(layout.view_size).y + 1;
+            let x = (self.terminal_size.x - self.message.chars().count() as i16) / 2;
+            self.back.set_color(Tint::Default);
+            self.back.print_at_pos(Pos{x: x.max(0), y}, &self.message);
         }
 
+        write_diff(&mut self.screen, &self.front, &self.back, self.depth).unwrap();
         self.flush();
+
+        std::mem::swap(&mut self.front, &mut self.back);
     }
+}
 
-    /* private methods */
+// The menu screens, put on screen the same double-buffered way
+pub struct MenuDrawer {
+    screen: Screen,
+    terminal_size: Board,
+    front: Frame,
+    back: Frame,
+}
 
-    fn set_color(&mut self, color: game::Color) {
-        write!(self.screen, "{}", color).unwrap();
+impl MenuDrawer {
+    pub fn new() -> MenuDrawer {
+        let terminal_size = GameDrawer::get_terminal_size();
+        MenuDrawer {
+            screen: stdout().into_raw_mode().unwrap(),
+            terminal_size,
+            front: Frame::new(terminal_size),
+            back: Frame::new(terminal_size),
+        }
     }
 
-    fn reset_color(&mut self) {
-        write!(self.screen, "{}", game::Color::White).unwrap();
+    // Starts over with a blank screen: the first time, on a resize, after
+    // a game was played on it.
+    pub fn reset(&mut self) {
+        self.terminal_size = GameDrawer::get_terminal_size();
+        self.front = Frame::new(self.terminal_size);
+        self.back = Frame::new(self.terminal_size);
+        write!(self.screen, "{}{}{}", termion::clear::All, termion::cursor::Hide, Tint::Default)
+            .unwrap();
     }
 
-    fn board_print_at_pos(&mut self, pos: Pos, s: &str) {
-        let pos = self.board_offset + pos;
-        self.print_at_pos(pos, s);
+    pub fn draw(&mut self, menu: &Menu) {
+        if GameDrawer::get_terminal_size() != self.terminal_size {
+            self.reset();
+        }
+
+        self.back.clear();
+        draw_menu(&mut self.back, self.terminal_size, menu);
+        write_diff(&mut self.screen, &self.front, &self.back, Depth::TrueColor).unwrap();
+        self.screen.flush().unwrap();
+
+        std::mem::swap(&mut self.front, &mut self.back);
     }
 
-    fn print_at_pos(&mut self, pos: Pos, s: &str) {
-        write!(self.screen, "{}{}", pos.into_cursor_pos(), s)
-            .unwrap();
+    pub fn fini(&mut self) {
+        write!(self.screen, "{}{}{}{}", termion::clear::All, cursor_pos(Pos{x: 0, y: 0}),
+               termion::style::Reset, termion::cursor::Show).unwrap();
+        self.screen.flush().unwrap();
     }
+}
 
-    fn draw_border(&mut self, game: &Game) {
-        let bs = if game.periodic_world {
-            SYMBOL_BORDER_PERIODIC
-        } else {
-            SYMBOL_BORDER
-        };
+// the terminal size a view needs: the border around it and a line
+// below for messages
+fn view_layout_size(view: Board, style: Style) -> Board {
+    style.scale(view + Board{x: 2, y: 0}) + Board{x: 0, y: 3}
+}
 
-        let border_base = self.board_offset - Pos{x: 1, y: 1};
-        let border = game.board + Board{x: 2, y: 2};
+// The whole board in the middle of the terminal if it fits; if not, as
+// much of it as fits, with the minimap next to it if asked for and there
+// is room. None if the terminal is too small to play at all.
+fn board_layout(board: Board, terminal_size: Board, minimap: bool, style: Style)
+    -> Option<Layout>
+{
+    let fit = |room: Board| {
+        let room = room - view_layout_size(Board{x: 0, y: 0}, style);
+        Board{x: (room.x / style.cell_width()).min(board.x), y: room.y.min(board.y)}
+    };
+    let too_small = |view: Board| {
+        view.x < MIN_VIEW_SIZE.x.min(board.x) || view.y < MIN_VIEW_SIZE.y.min(board.y)
+    };
+
+    let mut view_size = fit(terminal_size);
+    if too_small(view_size) {
+        return None;
+    }
 
-        self.print_at_pos(border_base, "");
-        for _ in 0 .. border.x {
-            write!(self.screen, "{}", &bs).unwrap();
+    let mut minimap_width = 0;
+    if minimap && view_size != board {
+        let map = style.for_minimap().scale(minimap_size(board));
+        let with_map = fit(terminal_size - Board{x: map.x + 1, y: 0});
+        if !too_small(with_map) && map.y <= with_map.y + 2 {
+            view_size = with_map;
+            minimap_width = map.x + 1;
         }
+    }
 
-        for y in 1 .. border.y - 1 {
-            self.print_at_pos(border_base + Pos{x: 0, y}, &bs);
-            self.print_at_pos(border_base + Pos{x: border.x - 1, y}, &bs);
-        }
+    let spare = terminal_size - view_layout_size(view_size, style) - Board{x: minimap_width, y: 0};
+    let board_offset = style.scale(Pos{x: 1, y: 1}) + Pos{x: spare.x / 2, y: spare.y / 2};
+
+    Some(Layout {
+        board_offset,
+        view_size,
+        minimap: if minimap_width > 0 {
+            Some(board_offset + style.scale(Pos{x: view_size.x + 1, y: -1}) + Pos{x: 1, y: 0})
+        } else {
+            None
+        },
+    })
+}
 
-        self.print_at_pos(border_base + Pos{x: 0, y: border.y - 1}, "");
-        for _ in 0 .. border.x {
-            write!(self.screen, "{}", &bs).unwrap();
+// Writes out the cells of `next` that differ from `prev`, moving the
+// cursor and switching colors only when needed. Expects the default color
+// to begin with, and leaves it so. The right halves of the wide symbols
+// are put on screen along with their left ones. The colors the terminal
+// lacks are replaced with the closest ones it has.
+pub fn write_diff(out: &mut dyn Write, prev: &Frame, next: &Frame, depth: Depth) -> io::Result<()> {
+    let size = next.size();
+    let mut cursor = None;
+    let mut color = Tint::Default;
+
+    for y in 0 .. size.y {
+        for x in 0 .. size.x {
+            let pos = Pos{x, y};
+            let cell = next.cell(pos);
+            if cell == prev.cell(pos) || cell.ch == WIDE_FILLER {
+                continue;
+            }
+
+            if cursor != Some(pos) {
+                write!(out, "{}", cursor_pos(pos))?;
+            }
+            let cell_color = cell.color.fit(depth);
+            if cell.ch != ' ' && color != cell_color {
+                write!(out, "{}", cell_color)?;
+                color = cell_color;
+            }
+            write!(out, "{}", cell.ch)?;
+
+            cursor = Some(pos + Pos{x: 1, y: 0});
         }
     }
 
-    fn draw_snake(&mut self, game: &Game) {
-        let board_offset = self.board_offset;
+    if color != Tint::Default {
+        write!(out, "{}", Tint::Default)?;
+    }
 
-        self.set_color(game.snake.color);
+    Ok(())
+}
 
-        for p in (&game.snake).into_iter().take(1) {
-            self.print_at_pos(board_offset + p, SYMBOL_SNAKE_HEAD);
+impl Renderer for GameDrawer {
+    fn init(&mut self, game: &Game) {
+        write!(self.screen, "{}{}{}",
+               termion::clear::All,
+               termion::cursor::Hide,
+               Tint::Default).unwrap();
+        self.front.clear();
+
+        if self.too_small() {
+            let board = game.board();
+            let need = view_layout_size(Board {
+                x: MIN_VIEW_SIZE.x.min(board.x),
+                y: MIN_VIEW_SIZE.y.min(board.y),
+            }, self.style);
+            let message = format!("Please enlarge the terminal to {}x{} to go on", need.x, need.y);
+            let message: String = message.chars().take(self.terminal_size.x as usize).collect();
+            write!(self.screen, "{}{}", cursor_pos(Pos{x: 0, y: 0}), message).unwrap();
+            self.flush();
+            return;
         }
 
-        for p in (&game.snake).into_iter().skip(1) {
-            self.print_at_pos(board_offset + p, SYMBOL_SNAKE_BODY);
-        }
+        self.present(game);
+    }
+
+    fn update(&mut self, game: &Game, _update: &GameUpdate) {
+        self.present(game);
+    }
+
+    fn fini(&mut self, _game: &Game) {
+        let last_line = Pos{x: 0, y: self.terminal_size.y - 1};
+        let pos = match self.layout {
+            Some(layout) => layout.board_offset + self.style.scale(layout.view_size),
+            None => last_line,
+        };
+
+        write!(self.screen, "{}\n\r{}{}\nGame over!\n\r\n",
+               cursor_pos(pos),
+               termion::style::Reset, termion::cursor::Show).unwrap();
+        self.flush();
+    }
 
-        self.reset_color();
+    fn rewind(&mut self, game: &Game) {
+        let player = self.player;
+        self.follow(game, player);
+        self.present(game);
     }
 
-    fn draw_food(&mut self, game: &Game) {
-        let board_offset = self.board_offset;
-        self.print_at_pos(board_offset + game.food.pos, SYMBOL_FOOD);
+    fn resize(&mut self, game: &Game, size: Board) {
+        self.terminal_size = size;
+        self.layout = board_layout(game.board(), size, self.show_minimap, self.style);
+        if let Some(layout) = self.layout {
+            self.view = Viewport::around(game, self.head(game), layout.view_size);
+        }
+        self.front = Frame::new(size);
+        self.back = Frame::new(size);
+        self.init(game);
     }
 }
+
+#[test]
+fn write_diff_test() {
+    use scene::Canvas;
+
+    let mut prev = Frame::new(Board{x: 8, y: 2});
+    prev.print_at_pos(Pos{x: 0, y: 0}, "ab");
+
+    let mut next = prev.clone();
+    next.set_color(Tint::Ansi(2));
+    next.print_at_pos(Pos{x: 3, y: 1}, "oo@");
+
+    let mut out = Vec::new();
+    write_diff(&mut out, &prev, &prev, Depth::TrueColor).unwrap();
+    assert!(out.is_empty());
+
+    // one cursor move and one color change for the three cells in a row
+    write_diff(&mut out, &prev, &next, Depth::TrueColor).unwrap();
+    let expected = format!("{}{}oo@{}", cursor_pos(Pos{x: 3, y: 1}),
+                           Tint::Ansi(2), Tint::Default);
+    assert_eq!(expected, String::from_utf8(out).unwrap());
+
+    // no truecolor, the closest palette color
+    next.set_color(Tint::Rgb(250, 0, 0));
+    next.print_at_pos(Pos{x: 0, y: 1}, "x");
+    let mut out = Vec::new();
+    write_diff(&mut out, &prev, &next, Depth::Ansi16).unwrap();
+    let expected = format!("{}{}x{}", cursor_pos(Pos{x: 0, y: 1}), Tint::Ansi(9), cursor_pos(Pos{x: 3, y: 1}));
+    assert!(String::from_utf8(out).unwrap().starts_with(&expected));
+}
+
+#[test]
+fn board_layout_test() {
+    let board = Board{x: 16, y: 16};
+    let narrow = Style::default();
+    let full = |x, y| Some(Layout {
+        board_offset: Pos{x, y}, view_size: board, minimap: None,
+    });
+    assert_eq!(full(1, 1), board_layout(board, Board{x: 18, y: 19}, false, narrow));
+    assert_eq!(full(32, 3), board_layout(board, Board{x: 80, y: 24}, true, narrow));
+
+    // too small for the whole board, but good for a part of it
+    let layout = board_layout(board, Board{x: 80, y: 12}, false, narrow).unwrap();
+    assert_eq!(Board{x: 16, y: 9}, layout.view_size);
+
+    let big = Board{x: 100, y: 40};
+    let layout = board_layout(big, Board{x: 80, y: 24}, true, narrow).unwrap();
+    assert_eq!(Board{x: 57, y: 21}, layout.view_size);
+    assert_eq!(Some(Pos{x: 60, y: 0}), layout.minimap);
+
+    assert_eq!(None, board_layout(board, Board{x: 11, y: 40}, false, narrow));
+    assert_eq!(None, board_layout(board, Board{x: 80, y: 8}, false, narrow));
+
+    // square cells take twice the columns
+    let square = Style { square_cells: true, ..Style::default() };
+    assert_eq!(full(2, 1), board_layout(board, Board{x: 36, y: 19}, false, square));
+    let layout = board_layout(board, Board{x: 30, y: 19}, false, square).unwrap();
+    assert_eq!(Board{x: 13, y: 16}, layout.view_size);
+    assert_eq!(Pos{x: 2, y: 1}, layout.board_offset);
+}
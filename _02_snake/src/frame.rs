@@ -0,0 +1,107 @@
+// Frame: the whole scene as a grid of colored characters
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+#[cfg(test)]
+use scene;
+use scene::Canvas;
+use theme::Tint;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Cell {
+    pub ch: char,
+    pub color: Tint,
+}
+
+pub const EMPTY_CELL: Cell = Cell { ch: ' ', color: Tint::Default };
+
+#[cfg(test)]
+const TINT_KEYS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+#[derive(Clone)]
+pub struct Frame {
+    size: Board,
+    cells: Vec<Cell>,
+    color: Tint,
+}
+
+impl Frame {
+    pub fn new(size: Board) -> Frame {
+        Frame {
+            size,
+            cells: vec![EMPTY_CELL; size.x as usize * size.y as usize],
+            color: Tint::Default,
+        }
+    }
+
+    pub fn size(&self) -> Board {
+        self.size
+    }
+
+    pub fn cell(&self, pos: Pos) -> Cell {
+        self.cells[(pos.y * self.size.x + pos.x) as usize]
+    }
+
+    pub fn clear(&mut self) {
+        for c in self.cells.iter_mut() {
+            *c = EMPTY_CELL;
+        }
+        self.color = Tint::Default;
+    }
+
+    // The characters, then an empty line and the colors of the same
+    // cells, one line per row in both. The default color is a dot, the
+    // others are letters and digits in the order they show up, listed at
+    // the end.
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+
+        for row in self.cells.chunks(self.size.x as usize) {
+            text.extend(row.iter().map(|c| c.ch).filter(|&ch| ch != scene::WIDE_FILLER));
+            text.push('\n');
+        }
+
+        text.push('\n');
+
+        let mut tints = Vec::new();
+        for row in self.cells.chunks(self.size.x as usize) {
+            for c in row {
+                if c.color == Tint::Default {
+                    text.push('.');
+                    continue;
+                }
+                let idx = tints.iter().position(|&t| t == c.color).unwrap_or_else(|| {
+                    tints.push(c.color);
+                    tints.len() - 1
+                });
+                text.push(TINT_KEYS.chars().nth(idx).expect("too many colors to tell apart"));
+            }
+            text.push('\n');
+        }
+
+        for (i, tint) in tints.iter().enumerate() {
+            text += &format!("{}: {:?}\n", TINT_KEYS.chars().nth(i).unwrap(), tint);
+        }
+
+        text
+    }
+}
+
+impl Canvas for Frame {
+    fn print_at_pos(&mut self, pos: Pos, s: &str) {
+        for (i, ch) in s.chars().enumerate() {
+            let x = pos.x + i as i16;
+            if x < 0 || x >= self.size.x || pos.y < 0 || pos.y >= self.size.y {
+                continue;
+            }
+            // a blank looks the same in any color
+            let color = if ch == ' ' { Tint::Default } else { self.color };
+            self.cells[(pos.y * self.size.x + x) as usize] = Cell { ch, color };
+        }
+    }
+
+    fn set_color(&mut self, color: Tint) {
+        self.color = color;
+    }
+}
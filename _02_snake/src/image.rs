@@ -0,0 +1,346 @@
+// Images: the game as pictures, an SVG of a moment or a GIF of a replay
+//
+// Both draw the same picture: the board with the border around it, one
+// square per cell in the theme's colors. On hexagons, every odd row of
+// the board goes half a cell to the right, the way bricks are laid. The
+// symbols of the theme don't show, the shapes tell the walls, the snake,
+// the food and the hazards apart.
+
+extern crate gif;
+
+use std::borrow::Cow;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::Game;
+use snake_engine::topology::Edge;
+use snake_engine::tiling::Tiling;
+use snake_engine::{Controller, Recording};
+
+use scene::Style;
+use theme::Tint;
+
+type Rgb = (u8, u8, u8);
+
+// pixels a cell takes each way
+const CELL: i16 = 16;
+
+const BACKGROUND: Rgb = (24, 24, 24);
+// what the default color of the terminal stands for
+const FOREGROUND: Rgb = (204, 204, 204);
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Shape {
+    Wall,       // the whole cell
+    Segment,    // a pixel off each side, to tell the segments apart
+    Head,       // a circle
+    Food,       // a smaller one
+    Hazard,     // a diamond
+}
+
+// the picture cell by cell, the border included
+struct Picture {
+    size: Board,
+    hex: bool,
+    cells: Vec<Option<(Rgb, Shape)>>,
+}
+
+fn rgb(tint: Tint) -> Rgb {
+    tint.rgb().unwrap_or(FOREGROUND)
+}
+
+impl Picture {
+    fn new(game: &Game, style: Style) -> Picture {
+        let theme = style.theme;
+        let size = game.board() + Board{x: 2, y: 2};
+        let mut picture = Picture {
+            size,
+            hex: game.tiling() == Tiling::Hex,
+            cells: vec![None; size.x as usize * size.y as usize],
+        };
+
+        // the edges that lead somewhere are no walls, only a hint of the
+        // edge, a brighter one where the board comes back mirrored
+        let topology = game.topology();
+        let border = |edge: Edge| {
+            let (r, g, b) = rgb(theme.border_color);
+            match edge {
+                Edge::Wall => theme.border_color,
+                Edge::Wrap => Tint::Rgb(r, g, b).shade(40),
+                Edge::Twist => Tint::Rgb(r, g, b).shade(70),
+            }
+        };
+        for y in 0 .. size.y {
+            for x in 0 .. size.x {
+                if y == 0 || y == size.y - 1 {
+                    picture.set(Pos{x, y}, rgb(border(topology.y)), Shape::Wall);
+                } else if x == 0 || x == size.x - 1 {
+                    picture.set(Pos{x, y}, rgb(border(topology.x)), Shape::Wall);
+                }
+            }
+        }
+
+        let offset = Pos{x: 1, y: 1};
+        picture.set(offset + game.food().pos, rgb(theme.food_color), Shape::Food);
+        for hazard in game.hazards() {
+            picture.set(offset + hazard.pos, rgb(theme.hazard_color), Shape::Hazard);
+        }
+
+        for snake in game.snakes() {
+            let color = theme.snake_color(snake.color);
+            for (i, p) in snake.into_iter().enumerate() {
+                let shape = if i == 0 { Shape::Head } else { Shape::Segment };
+                let tint = style.pattern.segment_color(color, i, snake.len());
+                picture.set(offset + p, rgb(tint), shape);
+            }
+        }
+
+        picture
+    }
+
+    fn set(&mut self, pos: Pos, rgb: Rgb, shape: Shape) {
+        self.cells[(pos.y * self.size.x + pos.x) as usize] = Some((rgb, shape));
+    }
+
+    // in pixels, the half cell of the odd rows included on hexagons
+    fn pixel_size(&self) -> Board {
+        let half = if self.hex { CELL / 2 } else { 0 };
+        Board{x: self.size.x * CELL + half, y: self.size.y * CELL}
+    }
+
+    // Where the cell at `pos` goes, in pixels, and how wide it is. The
+    // cells of the side walls stretch to the edges of the picture, where
+    // the odd rows leave half a cell.
+    fn place(&self, pos: Pos) -> (i16, i16, i16) {
+        let odd = self.hex && (pos.y - 1).rem_euclid(2) == 1;
+        let x = pos.x * CELL + if odd { CELL / 2 } else { 0 };
+        if pos.x == 0 {
+            (0, pos.y * CELL, x + CELL)
+        } else if pos.x == self.size.x - 1 {
+            (x, pos.y * CELL, self.pixel_size().x - x)
+        } else {
+            (x, pos.y * CELL, CELL)
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (Pos, Rgb, Shape)> + '_ {
+        let width = self.size.x;
+        self.cells.iter().enumerate().filter_map(move |(i, cell)| {
+            cell.map(|(rgb, shape)| (Pos{x: i as i16 % width, y: i as i16 / width}, rgb, shape))
+        })
+    }
+
+    fn svg(&self) -> String {
+        let Board{x: width, y: height} = self.pixel_size();
+        let mut svg = String::new();
+
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                       viewBox=\"0 0 {} {}\">", width, height, width, height).unwrap();
+        writeln!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                 width, height, hex(BACKGROUND)).unwrap();
+
+        for (pos, rgb, shape) in self.cells() {
+            let (x, y, cell_width) = self.place(pos);
+            let half = CELL / 2;
+            match shape {
+                Shape::Wall => writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" \
+                                              fill=\"{}\"/>", x, y, cell_width, CELL, hex(rgb)),
+                Shape::Segment => writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" \
+                                                 height=\"{}\" fill=\"{}\"/>",
+                                           x + 1, y + 1, CELL - 2, CELL - 2, hex(rgb)),
+                Shape::Head => writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+                                        x + half, y + half, half, hex(rgb)),
+                Shape::Food => writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+                                        x + half, y + half, CELL * 3 / 10, hex(rgb)),
+                Shape::Hazard => writeln!(svg, "<polygon points=\"{},{} {},{} {},{} {},{}\" fill=\"{}\"/>",
+                                          x + half, y, x + CELL, y + half, x + half, y + CELL,
+                                          x, y + half, hex(rgb)),
+            }.unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // the pixels, row by row
+    fn pixels(&self) -> Vec<Rgb> {
+        let Board{x: width, y: height} = self.pixel_size();
+        let mut pixels = vec![BACKGROUND; width as usize * height as usize];
+
+        for (pos, rgb, shape) in self.cells() {
+            // only the walls are any wider
+            let (left, top, cell_width) = self.place(pos);
+            for py in 0 .. CELL {
+                for px in 0 .. cell_width {
+                    // from the cell center, in half pixels
+                    let (dx, dy) = (2 * px + 1 - CELL, 2 * py + 1 - CELL);
+                    let r2 = (dx * dx + dy * dy) as i32;
+                    let inside = match shape {
+                        Shape::Wall => true,
+                        Shape::Segment => px > 0 && py > 0 && px < CELL - 1 && py < CELL - 1,
+                        Shape::Head => r2 <= (CELL * CELL) as i32,
+                        Shape::Food => 25 * r2 <= 9 * (CELL * CELL) as i32,
+                        Shape::Hazard => dx.abs() + dy.abs() <= CELL,
+                    };
+                    if inside {
+                        let (x, y) = (left + px, top + py);
+                        pixels[y as usize * width as usize + x as usize] = rgb;
+                    }
+                }
+            }
+        }
+
+        pixels
+    }
+}
+
+fn hex(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2)
+}
+
+pub fn svg(game: &Game, style: Style) -> String {
+    Picture::new(game, style).svg()
+}
+
+// The part of the picture that changed since `prev` (the whole one if
+// there is nothing before it) as a GIF frame with its own palette.
+fn gif_frame(prev: Option<&[Rgb]>, pixels: &[Rgb], width: usize) -> gif::Frame<'static> {
+    let height = pixels.len() / width;
+    let (mut left, mut top, mut right, mut bottom) = (0, 0, width, height);
+
+    if let Some(prev) = prev {
+        let changed: Vec<usize> = (0 .. pixels.len()).filter(|&i| prev[i] != pixels[i]).collect();
+        if changed.is_empty() {
+            // a pixel, just to take the time
+            right = 1;
+            bottom = 1;
+        } else {
+            left = changed.iter().map(|i| i % width).min().unwrap();
+            right = changed.iter().map(|i| i % width).max().unwrap() + 1;
+            top = changed[0] / width;
+            bottom = changed[changed.len() - 1] / width + 1;
+        }
+    }
+
+    let mut palette: Vec<Rgb> = Vec::new();
+    let mut buffer = Vec::with_capacity((right - left) * (bottom - top));
+    for y in top .. bottom {
+        for &rgb in &pixels[y * width + left .. y * width + right] {
+            let idx = palette.iter().position(|&c| c == rgb).unwrap_or_else(|| {
+                palette.push(rgb);
+                palette.len() - 1
+            });
+            buffer.push(idx as u8);
+        }
+    }
+
+    gif::Frame {
+        left: left as u16,
+        top: top as u16,
+        width: (right - left) as u16,
+        height: (bottom - top) as u16,
+        palette: Some(palette.iter().flat_map(|&(r, g, b)| vec![r, g, b]).collect()),
+        buffer: Cow::Owned(buffer),
+        ..gif::Frame::default()
+    }
+}
+
+// Plays the recording through, a GIF frame a tick, `delay` apiece. The
+// last frame stays a while longer.
+pub fn write_gif(recording: &Recording, style: Style, delay: Duration, out: &mut dyn Write)
+    -> io::Result<()>
+{
+    let to_io = |e: gif::EncodingError| io::Error::other(e);
+
+    let mut game = recording.game();
+    let mut script = recording.script();
+    let size = Picture::new(&game, style).pixel_size();
+
+    let mut encoder = gif::Encoder::new(out, size.x as u16, size.y as u16, &[]).map_err(to_io)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(to_io)?;
+
+    let centis = (delay.as_millis() / 10) as u16;
+    let mut prev: Option<Vec<Rgb>> = None;
+    loop {
+        let pixels = Picture::new(&game, style).pixels();
+        let over = game.exec(script.command(&game)).is_none();
+
+        let mut frame = gif_frame(prev.as_ref().map(|p| &p[..]), &pixels, size.x as usize);
+        frame.delay = if over { centis.max(100) * 3 } else { centis };
+        encoder.write_frame(&frame).map_err(to_io)?;
+
+        if over {
+            return Ok(());
+        }
+        prev = Some(pixels);
+    }
+}
+
+#[test]
+fn svg_simple_test() {
+    use snake_engine::Topology;
+
+    let game = Game::with_seed(Board{x: 10, y: 8}, 4, Topology::WALLS, 1);
+    let svg = svg(&game, Style::default());
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"192\" height=\"160\""));
+    // the head, the food and the rest of the snake in the theme's colors
+    assert_eq!(1, svg.matches("r=\"8\" fill=\"#00cd00\"").count());
+    assert_eq!(1, svg.matches("r=\"4\" fill=\"#cdcd00\"").count());
+    assert_eq!(3, svg.matches("width=\"14\" height=\"14\" fill=\"#00cd00\"").count());
+    assert!(svg.ends_with("</svg>\n"));
+
+    // on hexagons, half a cell wider, and the odd rows half a cell right
+    let game = Game::with_seed(Board{x: 10, y: 8}, 4, Topology::WALLS, 1).with_tiling(Tiling::Hex);
+    let hexagons = Picture::new(&game, Style::default()).svg();
+    assert!(hexagons.contains("width=\"200\" height=\"160\" viewBox"));
+    let head = game.snake().head();
+    let shift = if head.y % 2 == 1 { CELL / 2 } else { 0 };
+    let (cx, cy) = ((head.x + 1) * CELL + shift + 8, (head.y + 1) * CELL + 8);
+    assert!(hexagons.contains(&format!("cx=\"{}\" cy=\"{}\" r=\"8\"", cx, cy)));
+}
+
+#[test]
+fn gif_frame_test() {
+    let black = (0, 0, 0);
+    let white = (255, 255, 255);
+    let prev = vec![black; 12];
+    let mut next = prev.clone();
+    next[5] = white;
+    next[10] = white;
+
+    let frame = gif_frame(None, &next, 4);
+    assert_eq!((0, 0, 4, 3), (frame.left, frame.top, frame.width, frame.height));
+
+    // only the box around what changed
+    let frame = gif_frame(Some(&prev), &next, 4);
+    assert_eq!((1, 1, 2, 2), (frame.left, frame.top, frame.width, frame.height));
+    assert_eq!(&[0, 1, 1, 0][..], &frame.buffer[..]);
+    assert_eq!(Some(vec![255, 255, 255, 0, 0, 0]), frame.palette);
+}
+
+#[test]
+fn gif_simple_test() {
+    use snake_engine::{Command, Topology};
+    use snake_engine::game::Dir;
+
+    let mut recording = Recording::new(Board{x: 10, y: 8}, 4, Topology::TORUS, 1);
+    recording.push(Command::Move(Dir::Up));
+    recording.push(Command::Nop);
+
+    let mut out = Vec::new();
+    write_gif(&recording, Style::default(), Duration::from_millis(250), &mut out).unwrap();
+
+    // a frame before each tick, the quitting one included
+    let mut decoder = gif::DecodeOptions::new().read_info(&out[..]).unwrap();
+    assert_eq!((192, 160), (decoder.width(), decoder.height()));
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+    assert_eq!(vec![25, 25, 300], delays);
+}
@@ -0,0 +1,204 @@
+// Keyboard input: keymaps and the human player's controller
+
+use termion;
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+
+use snake_engine::Controller;
+use snake_engine::game::{Command, Dir, Game};
+use snake_engine::tiling::Tiling;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Keymap {
+    pub left: Key,
+    pub right: Key,
+    pub up: Key,
+    pub down: Key,
+    // on hexagons, instead of up and down
+    pub up_left: Key,
+    pub up_right: Key,
+    pub down_left: Key,
+    pub down_right: Key,
+    pub exit: Key,
+    pub rewind: Key,    // in practice, once the snake is out
+    // In the game, the left and right keys turn the snake a notch from
+    // where it heads and the others do nothing: two keys to play with.
+    pub relative: bool,
+}
+
+impl Keymap {
+    pub fn arrows() -> Keymap {
+        Keymap {
+            left: Key::Left,
+            right: Key::Right,
+            up: Key::Up,
+            down: Key::Down,
+            up_left: Key::Home,
+            up_right: Key::PageUp,
+            down_left: Key::End,
+            down_right: Key::PageDown,
+            exit: Key::Char('q'),
+            rewind: Key::Char('r'),
+            relative: false,
+        }
+    }
+
+    pub fn wasd() -> Keymap {
+        Keymap {
+            left: Key::Char('a'),
+            right: Key::Char('d'),
+            up: Key::Char('w'),
+            down: Key::Char('s'),
+            // around `s` on the keyboard
+            up_left: Key::Char('w'),
+            up_right: Key::Char('e'),
+            down_left: Key::Char('z'),
+            down_right: Key::Char('x'),
+            exit: Key::Char('q'),
+            rewind: Key::Char('r'),
+            relative: false,
+        }
+    }
+
+    // the keys that turn the snake on `tiling`, and where to
+    fn turns(&self, tiling: Tiling) -> Vec<(Key, Dir)> {
+        let mut turns = vec![(self.left, Dir::Left), (self.right, Dir::Right)];
+        match tiling {
+            Tiling::Square => turns.extend_from_slice(&[(self.up, Dir::Up), (self.down, Dir::Down)]),
+            Tiling::Hex => turns.extend_from_slice(&[
+                (self.up_left, Dir::UpLeft),
+                (self.up_right, Dir::UpRight),
+                (self.down_left, Dir::DownLeft),
+                (self.down_right, Dir::DownRight),
+            ]),
+        }
+        turns
+    }
+
+    // the keys as they are anywhere but in the game: each one a direction
+    pub fn command(&self, key: Key, tiling: Tiling) -> Command {
+        if key == self.exit {
+            return Command::Exit;
+        }
+        self.turns(tiling).into_iter()
+            .find(|&(k, _)| k == key)
+            .map_or(Command::Nop, |(_, dir)| Command::Move(dir))
+    }
+
+    // the keys steering the snake of `player`, relative to where it heads
+    // if the keymap is
+    pub fn steer(&self, key: Key, game: &Game, player: usize) -> Command {
+        let snake = match game.player_snake(player) {
+            Some(snake) if self.relative => snake,
+            _ => return self.command(key, game.tiling()),
+        };
+        match key {
+            _ if key == self.exit => Command::Exit,
+            _ if key == self.left => Command::Move(snake.dir.turn_left(game.tiling())),
+            _ if key == self.right => Command::Move(snake.dir.turn_right(game.tiling())),
+            _ => Command::Nop,
+        }
+    }
+}
+
+// how a key is called on the screen
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Human player: takes the last key pressed since the previous tick.
+/// There is one for the whole run, the menu reads its keys too.
+pub struct Keyboard {
+    keys: Keys<termion::AsyncReader>,
+    keymap: Keymap,
+}
+
+impl Keyboard {
+    pub fn new(keymap: Keymap) -> Keyboard {
+        Keyboard {
+            keys: termion::async_stdin().keys(),
+            keymap,
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // the next key pressed, if there is any
+    pub fn key(&mut self) -> Option<Key> {
+        match self.keys.next() {
+            Some(Ok(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    // the move for the snake of `player`, from the last key pressed
+    pub fn steer(&mut self, game: &Game, player: usize) -> Command {
+        match self.keys.by_ref().last() {
+            Some(Ok(key)) => self.keymap.steer(key, game, player),
+            _ => Command::Nop,
+        }
+    }
+}
+
+impl Controller for Keyboard {
+    fn command(&mut self, game: &Game) -> Command {
+        let player = game.snake().player;
+        self.steer(game, player)
+    }
+}
+
+/// Demo mode: a bot plays while the keyboard can still quit the game.
+pub struct Autopilot<'a> {
+    keyboard: &'a mut Keyboard,
+    bot: &'a mut dyn Controller,
+}
+
+impl<'a> Autopilot<'a> {
+    pub fn new(keyboard: &'a mut Keyboard, bot: &'a mut dyn Controller) -> Autopilot<'a> {
+        Autopilot { keyboard, bot }
+    }
+}
+
+impl<'a> Controller for Autopilot<'a> {
+    fn command(&mut self, game: &Game) -> Command {
+        match self.keyboard.command(game) {
+            Command::Exit => Command::Exit,
+            _ => self.bot.command(game),
+        }
+    }
+}
+
+#[test]
+fn keymap_simple_test() {
+    let keymap = Keymap::wasd();
+    assert_eq!(Command::Move(Dir::Up), keymap.command(Key::Char('w'), Tiling::Square));
+    assert_eq!(Command::Exit, keymap.command(Key::Char('q'), Tiling::Square));
+    assert_eq!(Command::Nop, keymap.command(Key::Up, Tiling::Square));
+    assert_eq!(Command::Move(Dir::UpLeft), keymap.command(Key::Char('w'), Tiling::Hex));
+    assert_eq!(Command::Nop, keymap.command(Key::Char('s'), Tiling::Hex));
+}
+
+#[test]
+fn keymap_relative_test() {
+    use snake_engine::{Board, Topology};
+
+    let keymap = Keymap { relative: true, ..Keymap::arrows() };
+    let game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::TORUS, 1);
+    assert_eq!(Dir::Right, game.snake().dir);
+    assert_eq!(Command::Move(Dir::Up), keymap.steer(Key::Left, &game, 0));
+    assert_eq!(Command::Move(Dir::Down), keymap.steer(Key::Right, &game, 0));
+    assert_eq!(Command::Nop, keymap.steer(Key::Up, &game, 0));
+    assert_eq!(Command::Exit, keymap.steer(Key::Char('q'), &game, 0));
+    // the menus still go by the arrows
+    assert_eq!(Command::Move(Dir::Up), keymap.command(Key::Up, Tiling::Square));
+
+    let game = game.with_tiling(Tiling::Hex);
+    assert_eq!(Command::Move(Dir::DownRight), keymap.steer(Key::Right, &game, 0));
+}
+
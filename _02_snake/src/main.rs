@@ -1,55 +1,480 @@
 extern crate termion;
+extern crate snake_engine;
 
-mod utypes;
-mod game;
+mod cast;
+mod client;
+mod config;
 mod draw;
+mod frame;
+mod image;
+mod keyboard;
+mod menu;
+mod scene;
+mod scores;
+#[cfg(test)]
+mod text;
+mod theme;
+mod viewport;
 
-use std::{thread, time};
+use std::{cmp, env, fs, io, process, thread, time};
+use std::io::Write;
+use std::path::PathBuf;
 
-use termion::event::Key;
-use termion::input::TermRead;
+use snake_engine::{Command, Controller, Game, GameOver, History, Recording, Renderer};
+use snake_engine::utypes::parse_board;
+use snake_engine::topology::Topology;
+use snake_engine::tiling::Tiling;
+use snake_engine::bots;
+use snake_engine::wire;
+use snake_engine::external;
+use snake_engine::external::External;
+use config::Config;
+use draw::{GameDrawer, MenuDrawer};
+use menu::{Action, Input, Menu};
+use scene::Style;
+use scores::{HighScores, Score};
+use theme::{Depth, Pattern};
+use keyboard::{key_name, Autopilot, Keyboard, Keymap};
 
-use utypes::Board;
-use game::Game;
-use draw::GameDrawer;
+const USAGE: &str = "usage: _02_snake [--play] [--practice] [--wasd] [--relative] \
+                     [--bot greedy|pathfinder|hamiltonian] \
+                     [--bot-cmd COMMAND] [--move-ms MS] [--bot-log FILE] \
+                     [--delay MS] [--board WxH] [--walls] \
+                     [--world walls|torus|cylinder|mobius|klein|projective|X/Y] \
+                     [--hex] [--hazards N] [--minimap] [--square] \
+                     [--theme unicode|ascii|emoji|contrast|colorblind] \
+                     [--pattern solid|gradient|stripes] [--colors 16|256|truecolor] \
+                     [--record FILE] [--cast|--svg|--gif FILE] [--connect [ADDR]] \
+                     [--watch [ADDR]|--replay FILE] [--follow N]";
 
-fn play() {
-    let mut stdin = termion::async_stdin().keys();
+struct Options {
+    config: Config,     // the saved settings, with the command line on top
+    skip_menu: bool,
+    keymap: Keymap,
+    delay: Option<u64>,
+    minimap: bool,
+    style: Style,
+    depth: Option<Depth>,
+    record: Option<PathBuf>,    // where the games are recorded to
+    export: Option<(Export, PathBuf)>,
+    connect: Option<String>,    // the server to play on
+    watch: bool,                // ... or just to watch the game on it
+    replay: Option<PathBuf>,    // a game the server recorded, to watch
+    follow: usize,              // the player the view follows when watching
+    bot_cmd: Option<String>,    // an external bot to play instead
+    move_ms: Option<u64>,       // how long it may think about a move
+    bot_log: Option<PathBuf>,   // where what it says goes
+}
+
+// what a recording can be turned into
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Export {
+    Cast,   // asciicast, the way the terminal shows the game
+    Svg,    // a picture of how the game ended
+    Gif,    // the whole game animated
+}
+
+// In practice, how many ticks a press of the rewind key takes back and
+// how many can be taken back in all
+const REWIND_TICKS: usize = 5;
+const HISTORY_TICKS: usize = 100;
+
+// bots are more fun to watch at a higher pace
+fn move_delay(opts: &Options) -> time::Duration {
+    let delay = opts.delay.unwrap_or(match (opts.config.bot, &opts.bot_cmd) {
+        (None, None) => opts.config.delay,
+        _ => opts.config.delay / 5,
+    });
+    time::Duration::from_millis(delay)
+}
+
+// the external bot, logging to where it was asked to
+fn start_bot(command: &str, opts: &Options) -> External {
+    let limit = opts.move_ms.map_or(external::DEFAULT_LIMIT, time::Duration::from_millis);
+    let mut bot = External::spawn(command, 0, limit).unwrap_or_else(|e| {
+        eprintln!("can't start {}: {}", command, e);
+        process::exit(1);
+    });
+    if let Some(ref path) = opts.bot_log {
+        match fs::File::create(path) {
+            Ok(file) => bot.log_to(Box::new(io::BufWriter::new(file))),
+            Err(e) => {
+                eprintln!("can't write {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+    }
+    bot
+}
+
+// Practice: the snake is out, but the game can go back a few ticks for
+// every press of the rewind key. Returns the move to play on with, none
+// to leave the game where it is.
+fn rewind(keyboard: &mut Keyboard, drawer: &mut GameDrawer, game: &mut Game, history: &mut History,
+          recording: &mut Recording) -> Option<Command>
+{
+    let keymap = *keyboard.keymap();
+    drawer.say(game, &format!("Out! {} to go back, {} to quit",
+                              key_name(keymap.rewind), key_name(keymap.exit)));
+    let mut back = 0;
+    loop {
+        if let Some(size) = drawer.terminal_resized() {
+            drawer.resize(game, size);
+        }
+
+        let key = match keyboard.key() {
+            Some(key) => key,
+            None => {
+                thread::sleep(time::Duration::from_millis(10));
+                continue;
+            }
+        };
+        if key == keymap.rewind {
+            let ticks = cmp::min(REWIND_TICKS, history.len());
+            if let Some(earlier) = history.rewind(ticks) {
+                *game = earlier;
+                let played = recording.commands.len() - ticks;
+                recording.commands.truncate(played);
+                back += ticks;
+                drawer.rewind(game);
+                drawer.say(game, &format!("{} ticks back: move to play on, {} to go back more",
+                                          back, key_name(keymap.rewind)));
+            }
+            continue;
+        }
+        match keymap.steer(key, game, game.snake().player) {
+            Command::Exit => return None,
+            Command::Move(dir) if back > 0 => {
+                drawer.say(game, "");
+                return Some(Command::Move(dir));
+            }
+            _ => (),
+        }
+    }
+}
+
+// plays a game through, returns how it ended
+fn play(keyboard: &mut Keyboard, opts: &Options) -> Game {
+    let config = &opts.config;
+    let delay = move_delay(opts);
+    let style = Style { theme: config.theme, ..opts.style };
+
+    let mut external = opts.bot_cmd.as_ref().map(|command| start_bot(command, opts));
+    let mut builtin = config.bot.map(|name| bots::by_name(name, 0).unwrap());
+    let mut bot: Option<&mut dyn Controller> = match (external.as_mut(), builtin.as_mut()) {
+        (Some(external), _) => Some(external),
+        (None, Some(bot)) => Some(bot.as_mut()),
+        (None, None) => None,
+    };
 
-    let mut game = Game::new(Board{x: 16, y: 16}, 4, true);
-    let mut drawer = GameDrawer::new(&game);
+    // taking moves back is only for the player to practice
+    let mut history = match bot {
+        None if config.practice => Some(History::new(HISTORY_TICKS)),
+        _ => None,
+    };
+    let mut next = None;    // the move that ended a rewind
+
+    let mut game = Game::new(config.board, 4, config.topology)
+        .with_tiling(config.tiling)
+        .with_hazards(config.hazards);
+    let mut recording = Recording::new(config.board, 4, config.topology, game.seed());
+    recording.tiling = config.tiling;
+    recording.hazards = config.hazards;
+    let depth = opts.depth.unwrap_or_else(Depth::detect);
+    let mut drawer = GameDrawer::new(&game, opts.minimap, style, depth);
 
     drawer.init(&game);
 
     loop {
-        use game::Dir;
-        use game::Command;
-
-        let key = stdin.by_ref().last();
-
-        let cmd = match key {
-            Some(Ok(Key::Char('q'))) => Command::Exit,
-            Some(Ok(Key::Left)) => Command::Move(Dir::Left),
-            Some(Ok(Key::Right)) => Command::Move(Dir::Right),
-            Some(Ok(Key::Up)) => Command::Move(Dir::Up),
-            Some(Ok(Key::Down)) => Command::Move(Dir::Down),
-            _ => Command::Nop,
+        if let Some(size) = drawer.terminal_resized() {
+            drawer.resize(&game, size);
+        }
+
+        let cmd = match (next.take(), bot.as_mut()) {
+            (Some(cmd), _) => cmd,
+            (None, Some(bot)) => Autopilot::new(keyboard, &mut **bot).command(&game),
+            (None, None) => keyboard.command(&game),
         };
 
+        // paused until the terminal is big enough again
+        if drawer.too_small() {
+            if cmd == Command::Exit {
+                break;
+            }
+            thread::sleep(cmp::min(delay, time::Duration::from_millis(100)));
+            continue;
+        }
+
+        if let Some(ref mut history) = history {
+            history.push(&game);
+        }
+        recording.push(cmd);
         let update = game.exec(cmd);
         if let Some(update) = update {
-            drawer.update_scene(&game, &update);
+            drawer.update(&game, &update);
         } else {
-            drawer.fini(&game);
-            break;
+            let died = game.over() != Some(GameOver::Exit) && game.over() != Some(GameOver::BoardFull);
+            match history {
+                Some(ref mut history) if died => {
+                    next = rewind(keyboard, &mut drawer, &mut game, history, &mut recording);
+                    if next.is_none() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
         }
 
-        thread::sleep(time::Duration::from_millis(500));
+        thread::sleep(delay);
     }
 
     drawer.fini(&game);
+
+    if let Some(why) = external.as_ref().and_then(|bot| bot.disqualified()) {
+        println!("The bot was disqualified: {}\r", why);
+    }
+
+    if let Some(ref path) = opts.record {
+        if let Err(e) = fs::write(path, recording.text()) {
+            eprintln!("could not save the recording to {}: {}\r", path.display(), e);
+        }
+    }
+
+    game
+}
+
+// the recording replayed into the standard output, at the pace of the game
+fn export(format: Export, path: &PathBuf, opts: &Options) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path.display(), e);
+        process::exit(1);
+    });
+    let recording = Recording::parse(&text).unwrap_or_else(|| {
+        eprintln!("{}: not a snake recording", path.display());
+        process::exit(1);
+    });
+
+    let style = Style { theme: opts.config.theme, ..opts.style };
+    let depth = opts.depth.unwrap_or(Depth::TrueColor);
+    let delay = time::Duration::from_millis(opts.delay.unwrap_or(opts.config.delay));
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        Export::Cast => cast::export(&recording, style, depth, delay, &mut out),
+        Export::Svg => out.write_all(image::svg(&recording.replay(), style).as_bytes()),
+        Export::Gif => image::write_gif(&recording, style, delay, &mut out),
+    }.unwrap_or_else(|e| {
+        eprintln!("could not export the recording: {}", e);
+        process::exit(1);
+    });
+}
+
+fn config_path(name: &str) -> Option<PathBuf> {
+    config::dir().map(|dir| dir.join(name))
+}
+
+// the menu until the player quits, a game each time they ask for one
+fn run_menu(keyboard: &mut Keyboard, opts: &mut Options) {
+    let scores = config_path("scores")
+        .map_or_else(HighScores::default, |path| HighScores::load(&path).unwrap_or_default());
+    let mut menu = Menu::new(opts.config.clone(), scores);
+    let mut drawer = MenuDrawer::new();
+    drawer.reset();
+
+    loop {
+        drawer.draw(&menu);
+
+        let key = match keyboard.key() {
+            Some(key) => key,
+            None => {
+                thread::sleep(time::Duration::from_millis(20));
+                continue;
+            }
+        };
+
+        match Input::from_key(keyboard.keymap(), key).and_then(|input| menu.input(input)) {
+            Some(Action::Play) => {
+                opts.config = menu.config.clone();
+                let game = play(keyboard, opts);
+                while keyboard.key().is_some() {}
+
+                let score = Score {
+                    length: game.snake().len(),
+                    mode: match opts.bot_cmd {
+                        Some(_) => "external",
+                        None => opts.config.mode(),
+                    }.to_string(),
+                    board: opts.config.board,
+                };
+                let length = score.length;
+                menu.message = Some(match menu.scores.add(score) {
+                    Some(rank) => format!("Game over: length {}, #{} in the high scores!",
+                                          length, rank + 1),
+                    None => format!("Game over: length {}", length),
+                });
+                if let Some(path) = config_path("scores") {
+                    if let Err(e) = menu.scores.save(&path) {
+                        menu.message = Some(format!("Could not save the high scores: {}", e));
+                    }
+                }
+
+                drawer.reset();
+            }
+            Some(Action::SaveConfig) => {
+                if let Some(path) = config_path("config") {
+                    if let Err(e) = menu.config.save(&path) {
+                        menu.message = Some(format!("Could not save the settings: {}", e));
+                    }
+                }
+            }
+            Some(Action::Quit) => break,
+            None => (),
+        }
+    }
+
+    drawer.fini();
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut config = match config_path("config").map(|path| Config::load(&path)) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("could not read the settings, using the defaults: {}", e);
+            Config::default()
+        }
+        None => Config::default(),
+    };
+
+    let mut skip_menu = false;
+    let mut keymap = Keymap::arrows();
+    let mut delay = None;
+    let mut minimap = false;
+    let mut style = Style::default();
+    let mut depth = None;
+    let mut record = None;
+    let mut export = None;
+    let mut connect = None;
+    let mut watch = false;
+    let mut replay = None;
+    let mut follow = 0;
+    let mut bot_cmd = None;
+    let mut move_ms = None;
+    let mut bot_log = None;
+
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--play" => skip_menu = true,
+            "--wasd" => keymap = Keymap { relative: keymap.relative, ..Keymap::wasd() },
+            "--relative" => keymap.relative = true,
+            "--bot" => {
+                let name = value();
+                config.bot = Some(bots::NAMES.iter().find(|&&n| n == name).unwrap_or_else(|| usage()));
+            }
+            "--delay" => delay = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--board" => config.board = parse_board(&value()).unwrap_or_else(|| usage()),
+            "--walls" => config.topology = Topology::WALLS,
+            "--world" => config.topology = Topology::by_name(&value()).unwrap_or_else(|| usage()),
+            "--hex" => config.tiling = Tiling::Hex,
+            "--hazards" => config.hazards = value().parse().unwrap_or_else(|_| usage()),
+            "--practice" => {
+                config.practice = true;
+                config.bot = None;
+            }
+            "--minimap" => minimap = true,
+            "--square" => style.square_cells = true,
+            "--theme" => config.theme = theme::by_name(&value()).unwrap_or_else(|| usage()),
+            "--pattern" => style.pattern = Pattern::by_name(&value()).unwrap_or_else(|| usage()),
+            "--colors" => depth = Some(Depth::by_name(&value()).unwrap_or_else(|| usage())),
+            "--record" => record = Some(PathBuf::from(value())),
+            "--cast" => export = Some((Export::Cast, PathBuf::from(value()))),
+            "--svg" => export = Some((Export::Svg, PathBuf::from(value()))),
+            "--gif" => export = Some((Export::Gif, PathBuf::from(value()))),
+            "--connect" | "--watch" => {
+                watch = arg == "--watch";
+                connect = Some(match args.peek() {
+                    Some(addr) if !addr.starts_with("--") => args.next().unwrap(),
+                    _ => wire::DEFAULT_ADDR.to_string(),
+                })
+            }
+            "--replay" => replay = Some(PathBuf::from(value())),
+            "--follow" => follow = value().parse().unwrap_or_else(|_| usage()),
+            "--bot-cmd" => bot_cmd = Some(value()),
+            "--move-ms" => move_ms = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--bot-log" => bot_log = Some(PathBuf::from(value())),
+            _ => usage(),
+        }
+    }
+
+    if !config.topology.fits(config.board, config.tiling) {
+        eprintln!("hexagons don't go with the {} world on {} rows",
+                  config.topology.name(), config.board.y);
+        process::exit(1);
+    }
+
+    Options {
+        config,
+        skip_menu,
+        keymap,
+        delay,
+        minimap,
+        style,
+        depth,
+        record,
+        export,
+        connect,
+        watch,
+        replay,
+        follow,
+        bot_cmd,
+        move_ms,
+        bot_log,
+    }
 }
 
 fn main() {
-    play();
+    let mut opts = parse_options();
+
+    if let Some((format, ref path)) = opts.export {
+        export(format, path, &opts);
+        return;
+    }
+
+    let mut keyboard = Keyboard::new(opts.keymap);
+
+    let style = Style { theme: opts.config.theme, ..opts.style };
+    let depth = opts.depth.unwrap_or_else(Depth::detect);
+    if let Some(ref path) = opts.replay {
+        if let Err(e) = client::replay(&mut keyboard, path, opts.follow, opts.minimap, style, depth) {
+            eprintln!("{}: {}", path.display(), e);
+            process::exit(1);
+        }
+    } else if let (Some(ref addr), true) = (&opts.connect, opts.watch) {
+        if let Err(e) = client::watch(&mut keyboard, addr, opts.follow, opts.minimap, style, depth) {
+            eprintln!("{}: {}", addr, e);
+            process::exit(1);
+        }
+    } else if let Some(ref addr) = opts.connect {
+        match client::play(&mut keyboard, addr, opts.config.board, opts.minimap, style, depth) {
+            Ok((ref game, player)) if game.players() > 1 && game.player_snake(player).is_some() => {
+                println!("You are the last one standing!");
+            }
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("{}: {}", addr, e);
+                process::exit(1);
+            }
+        }
+    } else if opts.skip_menu {
+        play(&mut keyboard, &opts);
+    } else {
+        run_menu(&mut keyboard, &mut opts);
+    }
 }
@@ -0,0 +1,382 @@
+// Menu: the screens before and between the games
+
+use termion::event::Key;
+
+use snake_engine::utypes::{Board, Pos};
+use snake_engine::bots;
+use snake_engine::game::{Color, Command, Dir};
+use snake_engine::topology;
+use snake_engine::topology::Topology;
+use snake_engine::tiling::Tiling;
+
+use config::Config;
+use keyboard::Keymap;
+use scene::Canvas;
+use scores::HighScores;
+use theme;
+use theme::Tint;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Input {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+impl Input {
+    // the game keys move around, Enter picks, the exit key goes back
+    pub fn from_key(keymap: &Keymap, key: Key) -> Option<Input> {
+        match keymap.command(key, Tiling::Square) {
+            Command::Move(Dir::Up) => Some(Input::Up),
+            Command::Move(Dir::Down) => Some(Input::Down),
+            Command::Move(Dir::Left) => Some(Input::Left),
+            Command::Move(Dir::Right) => Some(Input::Right),
+            Command::Exit => Some(Input::Back),
+            _ if key == Key::Char('\n') => Some(Input::Select),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Action {
+    Play,
+    Quit,
+    SaveConfig,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Page {
+    Main,
+    Mode,
+    Level,
+    Settings,
+    Scores,
+}
+
+// the level presets: a board and the shape of the world
+const LEVELS: [(&str, Board, Topology); 5] = [
+    ("Tiny", Board{x: 10, y: 8}, Topology::WALLS),
+    ("Classic", Board{x: 16, y: 16}, Topology::TORUS),
+    ("Wide", Board{x: 40, y: 16}, Topology::TORUS),
+    ("Big", Board{x: 60, y: 30}, Topology::WALLS),
+    ("Huge", Board{x: 100, y: 40}, Topology::WALLS),
+];
+
+const MIN_BOARD: i16 = 5;
+const MAX_BOARD: i16 = 250;
+const DELAY_STEP: u64 = 50;
+const MAX_DELAY: u64 = 1000;
+const MAX_HAZARDS: i16 = 10;
+
+pub struct Menu {
+    page: Page,
+    cursor: usize,
+    pub config: Config,
+    pub scores: HighScores,
+    pub message: Option<String>,    // how the last game went
+}
+
+impl Menu {
+    pub fn new(config: Config, scores: HighScores) -> Menu {
+        Menu { page: Page::Main, cursor: 0, config, scores, message: None }
+    }
+
+    // The title and the lines of the page, and the line the cursor is on
+    // if there is anything to pick.
+    fn lines(&self) -> (&'static str, Vec<String>, Option<usize>) {
+        let config = &self.config;
+        match self.page {
+            Page::Main => ("S N A K E", vec![
+                "New game".to_string(),
+                format!("Mode: {}", config.mode()),
+                format!("Level: {}x{}, {}{}{}", config.board.x, config.board.y,
+                        config.topology.name(),
+                        if config.tiling == Tiling::Hex { ", hex" } else { "" },
+                        match config.hazards {
+                            0 => String::new(),
+                            n => format!(", {} hazards", n),
+                        }),
+                "Settings".to_string(),
+                "High scores".to_string(),
+                "Quit".to_string(),
+            ], Some(self.cursor)),
+            Page::Mode => {
+                let mut lines = vec!["Player".to_string()];
+                lines.extend(bots::NAMES.iter().map(|name| format!("Bot: {}", name)));
+                lines.push("Practice (with rewind)".to_string());
+                ("Who plays", lines, Some(self.cursor))
+            }
+            Page::Level => ("Level", LEVELS.iter().map(|&(name, board, topology)| {
+                format!("{:8} {}x{}, {}", name, board.x, board.y, topology.name())
+            }).collect(), Some(self.cursor)),
+            Page::Settings => ("Settings", vec![
+                format!("Board width: {}", config.board.x),
+                format!("Board height: {}", config.board.y),
+                format!("Speed: {} ms per move", config.delay),
+                format!("World: {}", config.topology.name()),
+                format!("Grid: {}", config.tiling.name()),
+                format!("Hazards: {}", config.hazards),
+                format!("Theme: {}", config.theme.name),
+                "Back".to_string(),
+            ], Some(self.cursor)),
+            Page::Scores => {
+                let mut lines: Vec<String> = self.scores.scores().iter().enumerate()
+                    .map(|(i, s)| format!("{:2}. {:4}  {:12} {}x{}",
+                                          i + 1, s.length, s.mode, s.board.x, s.board.y))
+                    .collect();
+                if lines.is_empty() {
+                    lines.push("No games yet".to_string());
+                }
+                ("High scores", lines, None)
+            }
+        }
+    }
+
+    pub fn input(&mut self, input: Input) -> Option<Action> {
+        let count = self.lines().1.len();
+
+        match (self.page, input) {
+            (Page::Scores, Input::Up) | (Page::Scores, Input::Down) => None,
+            (Page::Scores, _) => self.go(Page::Main, 4),
+            (_, Input::Up) => {
+                self.cursor = (self.cursor + count - 1) % count;
+                None
+            }
+            (_, Input::Down) => {
+                self.cursor = (self.cursor + 1) % count;
+                None
+            }
+            (_, Input::Back) => self.back(),
+            (Page::Settings, Input::Left) => self.adjust(-1),
+            (Page::Settings, Input::Right) => self.adjust(1),
+            (_, Input::Left) => None,
+            (_, Input::Right) | (_, Input::Select) => self.select(),
+        }
+    }
+
+    fn go(&mut self, page: Page, cursor: usize) -> Option<Action> {
+        self.page = page;
+        self.cursor = cursor;
+        None
+    }
+
+    // back to the main page, on the item that led here
+    fn back(&mut self) -> Option<Action> {
+        match self.page {
+            Page::Main => Some(Action::Quit),
+            Page::Mode => self.go(Page::Main, 1),
+            Page::Level => self.go(Page::Main, 2),
+            Page::Settings => {
+                self.go(Page::Main, 3);
+                Some(Action::SaveConfig)
+            }
+            Page::Scores => self.go(Page::Main, 4),
+        }
+    }
+
+    fn select(&mut self) -> Option<Action> {
+        match (self.page, self.cursor) {
+            (Page::Main, 0) => Some(Action::Play),
+            (Page::Main, 1) => {
+                let current = match self.config.bot {
+                    Some(bot) => bots::NAMES.iter().position(|&n| n == bot).map_or(0, |i| i + 1),
+                    None if self.config.practice => bots::NAMES.len() + 1,
+                    None => 0,
+                };
+                self.go(Page::Mode, current)
+            }
+            (Page::Main, 2) => {
+                let current = LEVELS.iter()
+                    .position(|&(_, board, topology)| {
+                        board == self.config.board && topology == self.config.topology
+                    })
+                    .unwrap_or(0);
+                self.go(Page::Level, current)
+            }
+            (Page::Main, 3) => self.go(Page::Settings, 0),
+            (Page::Main, 4) => self.go(Page::Scores, 0),
+            (Page::Main, _) => Some(Action::Quit),
+            (Page::Mode, i) => {
+                self.config.bot = if i == 0 { None } else { bots::NAMES.get(i - 1).cloned() };
+                self.config.practice = i == bots::NAMES.len() + 1;
+                self.back();
+                Some(Action::SaveConfig)
+            }
+            (Page::Level, i) => {
+                let (_, board, topology) = LEVELS[i];
+                self.config.board = board;
+                self.config.topology = topology;
+                self.back();
+                Some(Action::SaveConfig)
+            }
+            (Page::Settings, 7) => self.back(),
+            (Page::Settings, _) => self.adjust(1),
+            (Page::Scores, _) => self.back(),
+        }
+    }
+
+    // Changes the setting under the cursor a step up or down. On
+    // hexagons, the heights and the worlds that don't fit are stepped
+    // past, and there are no hexagons on a world they don't fit.
+    fn adjust(&mut self, step: i16) -> Option<Action> {
+        let config = &mut self.config;
+        let resize = |n: i16| (n + step).clamp(MIN_BOARD, MAX_BOARD);
+
+        match self.cursor {
+            0 => config.board.x = resize(config.board.x),
+            1 => {
+                let (topology, tiling, x) = (config.topology, config.tiling, config.board.x);
+                let fits = |y| topology.fits(Board{x, y}, tiling);
+                let y = resize(config.board.y);
+                let y = if fits(y) { y } else { resize(y) };
+                if fits(y) {
+                    config.board.y = y;
+                }
+            }
+            2 => {
+                config.delay = if step < 0 {
+                    config.delay.saturating_sub(DELAY_STEP).max(DELAY_STEP)
+                } else {
+                    (config.delay + DELAY_STEP).min(MAX_DELAY)
+                };
+            }
+            3 => {
+                let shapes = &topology::NAMED;
+                let i = shapes.iter().position(|s| s.1 == config.topology).unwrap_or(0) as i16;
+                let n = shapes.len() as i16;
+                // the walls always fit
+                let mut i = i;
+                loop {
+                    i = (i + step).rem_euclid(n);
+                    if shapes[i as usize].1.fits(config.board, config.tiling) {
+                        break;
+                    }
+                }
+                config.topology = shapes[i as usize].1;
+            }
+            4 => {
+                let tiling = match config.tiling {
+                    Tiling::Square => Tiling::Hex,
+                    Tiling::Hex => Tiling::Square,
+                };
+                if config.topology.fits(config.board, tiling) {
+                    config.tiling = tiling;
+                } else {
+                    self.message = Some(format!("Hexagons don't go with the {} world on {} rows",
+                                                config.topology.name(), config.board.y));
+                }
+            }
+            5 => config.hazards = (config.hazards as i16 + step).clamp(0, MAX_HAZARDS) as usize,
+            6 => {
+                let themes = &theme::THEMES;
+                let i = themes.iter().position(|t| t == config.theme).unwrap_or(0) as i16;
+                let n = themes.len() as i16;
+                config.theme = &themes[(i + step).rem_euclid(n) as usize];
+            }
+            _ => (),
+        }
+        None
+    }
+}
+
+// The page in the middle of the canvas, the cursor line highlighted in
+// the color of the snake
+pub fn draw_menu(canvas: &mut dyn Canvas, size: Board, menu: &Menu) {
+    let (title, lines, cursor) = menu.lines();
+    let message = menu.message.as_ref();
+
+    let height = 2 + lines.len() as i16 + if message.is_some() { 2 } else { 0 };
+    let mut y = ((size.y - height) / 2).max(0);
+
+    let center = |canvas: &mut dyn Canvas, y: i16, s: &str| {
+        let x = (size.x - s.chars().count() as i16) / 2;
+        canvas.print_at_pos(Pos{x: x.max(0), y}, s);
+    };
+
+    center(canvas, y, title);
+    y += 2;
+
+    let highlight = menu.config.theme.snake_color(Color::Green);
+    for (i, line) in lines.iter().enumerate() {
+        if Some(i) == cursor {
+            canvas.set_color(highlight);
+            center(canvas, y, &format!("> {} <", line));
+            canvas.reset_color();
+        } else {
+            center(canvas, y, line);
+        }
+        y += 1;
+    }
+
+    if let Some(message) = message {
+        canvas.set_color(Tint::Default);
+        center(canvas, y + 1, message);
+    }
+}
+
+#[test]
+fn menu_simple_test() {
+    let mut menu = Menu::new(Config::default(), HighScores::default());
+    assert_eq!(Some(Action::Quit), menu.input(Input::Back));
+
+    // Settings, the board a cell wider
+    let mut menu = Menu::new(Config::default(), HighScores::default());
+    for _ in 0 .. 3 {
+        menu.input(Input::Down);
+    }
+    assert_eq!(None, menu.input(Input::Select));
+    assert_eq!(None, menu.input(Input::Right));
+    assert_eq!(Some(Action::SaveConfig), menu.input(Input::Back));
+    assert_eq!(Board{x: 17, y: 16}, menu.config.board);
+
+    // back on Settings in the main page, up to Mode and pick a bot
+    menu.input(Input::Up);
+    menu.input(Input::Up);
+    menu.input(Input::Select);
+    menu.input(Input::Down);
+    assert_eq!(Some(Action::SaveConfig), menu.input(Input::Select));
+    assert_eq!(Some("greedy"), menu.config.bot);
+
+    menu.input(Input::Up);
+    assert_eq!(Some(Action::Play), menu.input(Input::Select));
+}
+
+#[test]
+fn menu_input_test() {
+    let keymap = Keymap::wasd();
+    assert_eq!(Some(Input::Up), Input::from_key(&keymap, Key::Char('w')));
+    assert_eq!(Some(Input::Select), Input::from_key(&keymap, Key::Char('\n')));
+    assert_eq!(Some(Input::Back), Input::from_key(&keymap, Key::Char('q')));
+    assert_eq!(None, Input::from_key(&keymap, Key::Char('x')));
+}
+
+#[test]
+fn menu_hex_test() {
+    let config = Config { tiling: Tiling::Hex, ..Config::default() };
+    let mut menu = Menu::new(config, HighScores::default());
+
+    // a torus of hexagons takes an even number of rows, and no twist
+    menu.go(Page::Settings, 1);
+    menu.input(Input::Right);
+    assert_eq!(18, menu.config.board.y);
+    menu.go(Page::Settings, 3);
+    menu.input(Input::Right);
+    menu.input(Input::Right);
+    assert_eq!(Topology::WALLS, menu.config.topology);
+
+    // back to squares and on to the klein bottle, where hexagons don't go
+    menu.input(Input::Down);
+    menu.input(Input::Right);
+    menu.input(Input::Up);
+    menu.input(Input::Left);
+    menu.input(Input::Left);
+    assert_eq!("klein", menu.config.topology.name());
+    menu.input(Input::Down);
+    menu.input(Input::Right);
+    assert_eq!(Tiling::Square, menu.config.tiling);
+    assert!(menu.message.is_some());
+}
@@ -0,0 +1,252 @@
+// The scene: what the game looks like, drawn on any canvas
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::{Game, Snake};
+use snake_engine::topology::Edge;
+use snake_engine::tiling::Tiling;
+
+use theme;
+use theme::{Pattern, Theme, Tint};
+use viewport::Viewport;
+
+// the minimap is kept within this many cells
+const MINIMAP_MAX_SIZE: Board = Board{x: 20, y: 10};
+
+// stands for the right half of a symbol two columns wide
+pub const WIDE_FILLER: char = '\0';
+
+// How the board cells are laid out on the canvas, and what they look like
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Style {
+    // Two columns for each board cell. Terminal cells are about twice as
+    // tall as they are wide, so this is what makes the board cells square.
+    pub square_cells: bool,
+    // Hexagons: the cells twice as far apart, for every odd row of the
+    // board to go half a cell to the right. Up to the game, see `for_game`.
+    pub hex: bool,
+    pub theme: &'static Theme,
+    pub pattern: Pattern,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style { square_cells: false, hex: false, theme: theme::default(), pattern: Pattern::Solid }
+    }
+}
+
+impl Style {
+    // the style to draw `game` with, the tiling of its board taken in
+    pub fn for_game(self, game: &Game) -> Style {
+        Style { hex: game.tiling() == Tiling::Hex, ..self }
+    }
+
+    // the themes with wide symbols have square cells anyway
+    fn symbol_width(self) -> i16 {
+        if self.square_cells || self.theme.wide_glyphs { 2 } else { 1 }
+    }
+
+    // from one board cell to the next one on the canvas
+    pub fn cell_width(self) -> i16 {
+        if self.hex { 2 * self.symbol_width() } else { self.symbol_width() }
+    }
+
+    // the canvas offset of a board cell from the top-left one
+    pub fn scale(self, pos: Pos) -> Pos {
+        Pos{x: pos.x * self.cell_width(), y: pos.y}
+    }
+
+    // the same for a cell of the view on `game`, the odd rows of the
+    // board going half a cell to the right on hexagons
+    fn place(self, game: &Game, view: &Viewport, pos: Pos) -> Pos {
+        let row = (view.origin.y + pos.y) % game.board().y;
+        let shift = if self.hex && row % 2 == 1 { self.symbol_width() } else { 0 };
+        self.scale(pos) + Pos{x: shift, y: 0}
+    }
+
+    // the minimap's cells stand for blocks of board cells, side by side
+    // even on hexagons
+    pub fn for_minimap(self) -> Style {
+        Style { hex: false, ..self }
+    }
+
+    // a symbol filling a whole board cell (on hexagons, the half of it
+    // the symbol is in)
+    fn cell(self, symbol: &str) -> String {
+        if self.theme.wide_glyphs {
+            let mut cell = symbol.to_string();
+            cell.push(WIDE_FILLER);
+            cell
+        } else {
+            symbol.repeat(self.symbol_width() as usize)
+        }
+    }
+}
+
+/// Whatever the scene is drawn on: the terminal, a buffer in memory.
+pub trait Canvas {
+    fn print_at_pos(&mut self, pos: Pos, s: &str);
+    fn set_color(&mut self, color: Tint);
+
+    fn reset_color(&mut self) {
+        self.set_color(Tint::Default);
+    }
+}
+
+// The scene drawing, the same whatever the canvas is. The scene is
+// always drawn as a whole, renderers work out what actually changed.
+// `board_offset` is where the top-left cell of the view goes on the
+// canvas, the border is drawn around it.
+
+pub fn draw_scene(canvas: &mut dyn Canvas, board_offset: Pos, game: &Game, view: &Viewport,
+                  style: Style) {
+    draw_border(canvas, board_offset, game, view, style);
+    for snake in game.snakes() {
+        draw_snake(canvas, board_offset, game, snake, view, style);
+    }
+    draw_food(canvas, board_offset, game, view, style);
+    draw_hazards(canvas, board_offset, game, view, style);
+}
+
+// the whole board scaled down, each cell standing for a square of cells
+pub fn draw_minimap(canvas: &mut dyn Canvas, offset: Pos, game: &Game, style: Style) {
+    let style = style.for_minimap();
+    let theme = style.theme;
+    let scale = minimap_scale(game.board());
+    let size = minimap_size(game.board());
+    let to_map = |p: Pos| offset + style.scale(Pos{x: p.x / scale, y: p.y / scale});
+
+    for y in 0 .. size.y {
+        for x in 0 .. size.x {
+            canvas.print_at_pos(offset + style.scale(Pos{x, y}), &style.cell(theme.map_empty));
+        }
+    }
+
+    for snake in game.snakes() {
+        canvas.set_color(theme.snake_color(snake.color));
+        for p in snake.into_iter().skip(1) {
+            canvas.print_at_pos(to_map(p), &style.cell(theme.body));
+        }
+    }
+
+    canvas.set_color(theme.food_color);
+    canvas.print_at_pos(to_map(game.food().pos), &style.cell(theme.food));
+
+    canvas.set_color(theme.hazard_color);
+    for hazard in game.hazards() {
+        canvas.print_at_pos(to_map(hazard.pos), &style.cell(theme.hazard(hazard.motion)));
+    }
+
+    for snake in game.snakes() {
+        canvas.set_color(theme.snake_color(snake.color));
+        canvas.print_at_pos(to_map(snake.head()), &style.cell(theme.head(snake.dir)));
+    }
+    canvas.reset_color();
+}
+
+pub fn minimap_size(board: Board) -> Board {
+    let scale = minimap_scale(board);
+    Board{x: (board.x + scale - 1) / scale, y: (board.y + scale - 1) / scale}
+}
+
+fn minimap_scale(board: Board) -> i16 {
+    let scale_x = (board.x + MINIMAP_MAX_SIZE.x - 1) / MINIMAP_MAX_SIZE.x;
+    let scale_y = (board.y + MINIMAP_MAX_SIZE.y - 1) / MINIMAP_MAX_SIZE.y;
+    scale_x.max(scale_y).max(1)
+}
+
+// The border around the view, each side showing what lies beyond the
+// board edge there: a wall, the opposite edge or the opposite edge
+// mirrored. The sides where the board goes on beyond the view are drawn
+// lighter, unless they wrap around and the view scrolls across them.
+fn draw_border(canvas: &mut dyn Canvas, board_offset: Pos, game: &Game, view: &Viewport,
+               style: Style) {
+    let theme = style.theme;
+    let board = game.board();
+    let topology = game.topology();
+    let side = |edge: Edge, at_edge: bool| {
+        match edge {
+            Edge::Wrap => theme.border_periodic,
+            _ if !at_edge => theme.border_scroll,
+            Edge::Wall => theme.border,
+            Edge::Twist => theme.border_twist,
+        }
+    };
+
+    let top = style.cell(side(topology.y, view.origin.y == 0));
+    let bottom = style.cell(side(topology.y, view.origin.y + view.size.y == board.y));
+    let left = style.cell(side(topology.x, view.origin.x == 0));
+    let right = style.cell(side(topology.x, view.origin.x + view.size.x == board.x));
+
+    let border_base = board_offset - style.scale(Pos{x: 1, y: 1});
+    let border = view.size + Board{x: 2, y: 2};
+    let at = |x, y| border_base + style.scale(Pos{x, y});
+
+    canvas.set_color(theme.border_color);
+
+    // a symbol after another, on hexagons the half cells included
+    let width = style.symbol_width();
+    for i in 0 ..= (border.x - 1) * style.cell_width() / width {
+        canvas.print_at_pos(at(0, 0) + Pos{x: i * width, y: 0}, &top);
+        canvas.print_at_pos(at(0, border.y - 1) + Pos{x: i * width, y: 0}, &bottom);
+    }
+
+    for y in 1 .. border.y - 1 {
+        canvas.print_at_pos(at(0, y), &left);
+        canvas.print_at_pos(at(border.x - 1, y), &right);
+    }
+
+    canvas.reset_color();
+}
+
+fn draw_snake(canvas: &mut dyn Canvas, board_offset: Pos, game: &Game, snake: &Snake,
+              view: &Viewport, style: Style) {
+    let theme = style.theme;
+    let color = theme.snake_color(snake.color);
+    let len = snake.len();
+
+    // the head goes last, a one cell snake is all head
+    for (i, p) in snake.into_iter().enumerate().skip(1) {
+        let symbol = if i == len - 1 { theme.tail } else { theme.body };
+        if let Some(p) = view.to_view(game, p) {
+            canvas.set_color(style.pattern.segment_color(color, i, len));
+            canvas.print_at_pos(board_offset + style.place(game, view, p), &style.cell(symbol));
+        }
+    }
+
+    if let Some(p) = view.to_view(game, snake.head()) {
+        canvas.set_color(style.pattern.segment_color(color, 0, len));
+        canvas.print_at_pos(board_offset + style.place(game, view, p),
+                            &style.cell(theme.head(snake.dir)));
+    }
+
+    canvas.reset_color();
+}
+
+fn draw_food(canvas: &mut dyn Canvas, board_offset: Pos, game: &Game, view: &Viewport,
+             style: Style) {
+    if let Some(p) = view.to_view(game, game.food().pos) {
+        canvas.set_color(style.theme.food_color);
+        canvas.print_at_pos(board_offset + style.place(game, view, p), &style.cell(style.theme.food));
+        canvas.reset_color();
+    }
+}
+
+fn draw_hazards(canvas: &mut dyn Canvas, board_offset: Pos, game: &Game, view: &Viewport,
+                style: Style) {
+    canvas.set_color(style.theme.hazard_color);
+    for hazard in game.hazards() {
+        if let Some(p) = view.to_view(game, hazard.pos) {
+            canvas.print_at_pos(board_offset + style.place(game, view, p),
+                                &style.cell(style.theme.hazard(hazard.motion)));
+        }
+    }
+    canvas.reset_color();
+}
+
+#[test]
+fn minimap_size_test() {
+    assert_eq!(Board{x: 16, y: 8}, minimap_size(Board{x: 16, y: 8}));
+    assert_eq!(Board{x: 20, y: 5}, minimap_size(Board{x: 40, y: 10}));
+    assert_eq!(Board{x: 10, y: 10}, minimap_size(Board{x: 100, y: 100}));
+}
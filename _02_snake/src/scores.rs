@@ -0,0 +1,98 @@
+// High scores: the longest snakes so far, one `length mode WxH` line each
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use snake_engine::utypes::{Board, parse_board};
+
+// how many are kept
+const MAX_SCORES: usize = 10;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Score {
+    pub length: usize,
+    pub mode: String,   // "player", the bot name or "external"
+    pub board: Board,
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct HighScores {
+    scores: Vec<Score>,     // the best first
+}
+
+impl HighScores {
+    pub fn parse(text: &str) -> HighScores {
+        let mut scores = HighScores::default();
+
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            let length = words.next().and_then(|w| w.parse().ok());
+            let mode = words.next();
+            let board = words.next().and_then(parse_board);
+            if let (Some(length), Some(mode), Some(board)) = (length, mode, board) {
+                scores.add(Score { length, mode: mode.to_string(), board });
+            }
+        }
+
+        scores
+    }
+
+    pub fn text(&self) -> String {
+        self.scores.iter()
+            .map(|s| format!("{} {} {}x{}\n", s.length, s.mode, s.board.x, s.board.y))
+            .collect()
+    }
+
+    pub fn scores(&self) -> &[Score] {
+        &self.scores
+    }
+
+    // the place the score takes, None if it isn't good enough
+    pub fn add(&mut self, score: Score) -> Option<usize> {
+        let rank = self.scores.iter().position(|s| s.length < score.length)
+            .unwrap_or(self.scores.len());
+        if rank >= MAX_SCORES {
+            return None;
+        }
+
+        self.scores.insert(rank, score);
+        self.scores.truncate(MAX_SCORES);
+        Some(rank)
+    }
+
+    pub fn load(path: &Path) -> io::Result<HighScores> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(HighScores::parse(&text)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(HighScores::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.text())
+    }
+}
+
+#[test]
+fn scores_simple_test() {
+    let board = Board{x: 16, y: 16};
+    let score = |length| Score { length, mode: "player".to_string(), board };
+
+    let mut scores = HighScores::default();
+    assert_eq!(Some(0), scores.add(score(10)));
+    assert_eq!(Some(0), scores.add(score(20)));
+    // the older one stays ahead on a tie
+    assert_eq!(Some(2), scores.add(score(10)));
+
+    for _ in 0 .. MAX_SCORES {
+        scores.add(score(30));
+    }
+    assert_eq!(None, scores.add(score(25)));
+    assert_eq!(MAX_SCORES, scores.scores().len());
+
+    assert_eq!(scores, HighScores::parse(&scores.text()));
+}
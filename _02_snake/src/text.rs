@@ -0,0 +1,151 @@
+// Headless renderer: keeps the frame in memory instead of showing it,
+// used to check what the game looks like without a terminal
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::Game;
+use snake_engine::game::GameUpdate;
+use snake_engine::render::Renderer;
+
+#[cfg(test)]
+use snake_engine::Controller;
+#[cfg(test)]
+use snake_engine::bots;
+#[cfg(test)]
+use snake_engine::topology::Topology;
+
+use scene::{draw_scene, Style};
+use frame::Frame;
+#[cfg(test)]
+use theme;
+#[cfg(test)]
+use theme::Pattern;
+use viewport::Viewport;
+
+pub struct TextRenderer {
+    frame: Frame,
+    style: Style,
+}
+
+impl TextRenderer {
+    pub fn new(game: &Game, style: Style) -> TextRenderer {
+        let style = style.for_game(game);
+        let size = style.scale(game.board() + Board{x: 2, y: 0}) + Board{x: 0, y: 2};
+        TextRenderer { frame: Frame::new(size), style }
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn init(&mut self, game: &Game) {
+        self.frame.clear();
+        let board_offset = self.style.scale(Pos{x: 1, y: 1});
+        draw_scene(&mut self.frame, board_offset, game, &Viewport::full(game.board()), self.style);
+    }
+
+    fn update(&mut self, game: &Game, _update: &GameUpdate) {
+        self.init(game);
+    }
+
+    fn fini(&mut self, _game: &Game) {}
+}
+
+// Plays `ticks` ticks of the game seeded with `seed`, and compares the
+// last frame with snapshots/<name>.txt. Run the tests with
+// UPDATE_SNAPSHOTS=1 to write the golden files instead.
+#[cfg(test)]
+fn check_snapshot(name: &str, mut game: Game, style: Style, controller: &mut dyn Controller,
+                  ticks: usize) {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    let mut renderer = TextRenderer::new(&game, style);
+    renderer.init(&game);
+
+    for _ in 0 .. ticks {
+        let cmd = controller.command(&game);
+        match game.exec(cmd) {
+            Some(update) => renderer.update(&game, &update),
+            None => break,
+        }
+    }
+    renderer.fini(&game);
+
+    let text = renderer.frame().text();
+
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "snapshots", &format!("{}.txt", name)]
+        .iter().collect();
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (UPDATE_SNAPSHOTS=1 creates it)", path.display(), e));
+    assert!(golden == text, "{} differs from the snapshot:\n{}", name, text);
+}
+
+#[test]
+fn snapshot_start_test() {
+    let game = Game::with_seed(Board{x: 16, y: 16}, 4, Topology::TORUS, 1);
+    check_snapshot("start", game, Style::default(), &mut bots::Greedy::new(0), 0);
+}
+
+#[test]
+fn snapshot_pathfinder_periodic_test() {
+    let game = Game::with_seed(Board{x: 16, y: 16}, 4, Topology::TORUS, 2);
+    check_snapshot("pathfinder_periodic", game, Style::default(), &mut bots::Pathfinder::new(0), 300);
+}
+
+#[test]
+fn snapshot_hamiltonian_walls_test() {
+    let game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::WALLS, 3);
+    check_snapshot("hamiltonian_walls", game, Style::default(), &mut bots::Hamiltonian::new(0), 500);
+}
+
+#[test]
+fn snapshot_klein_test() {
+    let game = Game::with_seed(Board{x: 30, y: 12}, 4, Topology::by_name("klein").unwrap(), 4);
+    check_snapshot("klein", game, Style::default(), &mut bots::Pathfinder::new(0), 200);
+}
+
+#[test]
+fn snapshot_hex_test() {
+    use snake_engine::Tiling;
+
+    let game = Game::with_seed(Board{x: 16, y: 10}, 4, Topology::WALLS, 5).with_tiling(Tiling::Hex);
+    check_snapshot("hex", game, Style::default(), &mut bots::Pathfinder::new(0), 120);
+}
+
+#[test]
+fn snapshot_hazards_test() {
+    let game = Game::with_seed(Board{x: 16, y: 12}, 4, Topology::TORUS, 6).with_hazards(4);
+    check_snapshot("hazards", game, Style::default(), &mut bots::Pathfinder::new(0), 60);
+}
+
+#[test]
+fn snapshot_square_cells_test() {
+    let game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::WALLS, 3);
+    let style = Style { square_cells: true, ..Style::default() };
+    check_snapshot("square_cells", game, style, &mut bots::Pathfinder::new(0), 40);
+}
+
+#[test]
+fn snapshot_emoji_theme_test() {
+    let game = Game::with_seed(Board{x: 12, y: 10}, 4, Topology::WALLS, 3);
+    let style = Style { theme: theme::by_name("emoji").unwrap(), ..Style::default() };
+    check_snapshot("emoji_theme", game, style, &mut bots::Pathfinder::new(0), 40);
+}
+
+#[test]
+fn snapshot_gradient_test() {
+    let game = Game::with_seed(Board{x: 16, y: 16}, 4, Topology::TORUS, 2);
+    let style = Style { pattern: Pattern::Gradient, ..Style::default() };
+    check_snapshot("gradient", game, style, &mut bots::Pathfinder::new(0), 100);
+}
@@ -0,0 +1,404 @@
+// Themes: the symbols and the colors the scene is drawn with
+
+use std::env;
+use std::fmt;
+
+use snake_engine::game;
+use snake_engine::game::{Dir, Motion};
+
+// A terminal color. The first 16 of the 256 palette colors are the
+// basic ones every color terminal has.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Tint {
+    Default,            // whatever the terminal uses for text
+    Ansi(u8),           // one of the 256 palette colors
+    Rgb(u8, u8, u8),    // truecolor
+}
+
+impl fmt::Display for Tint {
+    // the escape sequence switching the foreground to the tint
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use termion::color;
+        match *self {
+            Tint::Default => color::Fg(color::Reset).fmt(f),
+            Tint::Ansi(n) => color::Fg(color::AnsiValue(n)).fmt(f),
+            Tint::Rgb(r, g, b) => color::Fg(color::Rgb(r, g, b)).fmt(f),
+        }
+    }
+}
+
+impl Tint {
+    // what the tint looks like, None for the default one
+    pub fn rgb(self) -> Option<(u8, u8, u8)> {
+        match self {
+            Tint::Default => None,
+            Tint::Ansi(n) => Some(palette(n)),
+            Tint::Rgb(r, g, b) => Some((r, g, b)),
+        }
+    }
+
+    // the closest tint the terminal can show
+    pub fn fit(self, depth: Depth) -> Tint {
+        let limit = match depth {
+            Depth::TrueColor => return self,
+            Depth::Ansi256 => 256,
+            Depth::Ansi16 => 16,
+        };
+        match self {
+            Tint::Ansi(n) if (n as usize) < limit => self,
+            Tint::Ansi(_) | Tint::Rgb(..) => Tint::Ansi(nearest(self.rgb().unwrap(), limit)),
+            Tint::Default => self,
+        }
+    }
+
+    // the same hue, `percent` as bright
+    pub fn shade(self, percent: u16) -> Tint {
+        match self.rgb() {
+            Some((r, g, b)) => {
+                let f = |c: u8| (c as u16 * percent / 100) as u8;
+                Tint::Rgb(f(r), f(g), f(b))
+            }
+            None => self,
+        }
+    }
+}
+
+// how many colors the terminal has
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Depth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl Depth {
+    pub fn by_name(name: &str) -> Option<Depth> {
+        match name {
+            "16" => Some(Depth::Ansi16),
+            "256" => Some(Depth::Ansi256),
+            "truecolor" => Some(Depth::TrueColor),
+            _ => None,
+        }
+    }
+
+    // the terminals tell it with the environment, if at all
+    pub fn detect() -> Depth {
+        let var = |name| env::var(name).unwrap_or_default();
+        let colorterm = var("COLORTERM");
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Depth::TrueColor
+        } else if var("TERM").contains("256color") {
+            Depth::Ansi256
+        } else {
+            Depth::Ansi16
+        }
+    }
+}
+
+// the xterm palette
+fn palette(n: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+        (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+        (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+        (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match n {
+        0 ..= 15 => BASIC[n as usize],
+        16 ..= 231 => {
+            let i = (n - 16) as usize;
+            (CUBE[i / 36], CUBE[i / 6 % 6], CUBE[i % 6])
+        }
+        _ => {
+            let grey = 8 + 10 * (n - 232);
+            (grey, grey, grey)
+        }
+    }
+}
+
+// The palette color closest to `rgb` among the first `limit` ones. Black
+// is left out, it's what the background usually is.
+fn nearest(rgb: (u8, u8, u8), limit: usize) -> u8 {
+    let dist = |n: u8| {
+        let (r, g, b) = palette(n);
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, rgb.0) + d(g, rgb.1) + d(b, rgb.2)
+    };
+    (1 .. limit).map(|n| n as u8).filter(|&n| n != 16).min_by_key(|&n| dist(n)).unwrap()
+}
+
+// How the snake segments are colored, starting from the color of the
+// player's snake
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Pattern {
+    Solid,
+    Gradient,   // fading away from the head to the tail
+    Stripes,    // two segments bright, two dim
+}
+
+impl Pattern {
+    pub fn by_name(name: &str) -> Option<Pattern> {
+        match name {
+            "solid" => Some(Pattern::Solid),
+            "gradient" => Some(Pattern::Gradient),
+            "stripes" => Some(Pattern::Stripes),
+            _ => None,
+        }
+    }
+
+    // the color of the segment `idx` (the head is 0) of a snake `len` long
+    pub fn segment_color(self, color: Tint, idx: usize, len: usize) -> Tint {
+        match self {
+            Pattern::Solid => color,
+            Pattern::Gradient => {
+                let last = len.max(2) - 1;
+                color.shade((100 - 70 * idx.min(last) / last) as u16)
+            }
+            Pattern::Stripes if idx / 2 % 2 == 1 => color.shade(55),
+            Pattern::Stripes => color,
+        }
+    }
+}
+
+// the head looks the way the snake goes
+#[derive(Debug, PartialEq)]
+pub struct Arrows {
+    pub up: &'static str,
+    pub right: &'static str,
+    pub down: &'static str,
+    pub left: &'static str,
+    // on hexagons
+    pub up_left: &'static str,
+    pub up_right: &'static str,
+    pub down_left: &'static str,
+    pub down_right: &'static str,
+}
+
+// The symbols are one column wide, unless `wide_glyphs` is set: then
+// they take two columns each, and the board is drawn with square cells.
+#[derive(Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub wide_glyphs: bool,
+
+    pub border: &'static str,
+    pub border_periodic: &'static str,
+    pub border_twist: &'static str,
+    pub border_scroll: &'static str,
+    pub head: Arrows,
+    pub body: &'static str,
+    pub tail: &'static str,
+    pub food: &'static str,
+    pub patrol: &'static str,       // the hazards going back and forth
+    pub chaser: &'static str,       // and those after the snakes
+    pub map_empty: &'static str,
+
+    pub snake_colors: [Tint; 4],    // White, Blue, Green, Red
+    pub border_color: Tint,
+    pub food_color: Tint,
+    pub hazard_color: Tint,
+}
+
+impl Theme {
+    pub fn head(&self, dir: Dir) -> &'static str {
+        match dir {
+            Dir::Up => self.head.up,
+            Dir::Right => self.head.right,
+            Dir::Down => self.head.down,
+            Dir::Left => self.head.left,
+            Dir::UpLeft => self.head.up_left,
+            Dir::UpRight => self.head.up_right,
+            Dir::DownLeft => self.head.down_left,
+            Dir::DownRight => self.head.down_right,
+        }
+    }
+
+    pub fn hazard(&self, motion: Motion) -> &'static str {
+        match motion {
+            Motion::Patrol(_) => self.patrol,
+            Motion::Chase => self.chaser,
+        }
+    }
+
+    pub fn snake_color(&self, color: game::Color) -> Tint {
+        match color {
+            game::Color::White => self.snake_colors[0],
+            game::Color::Blue => self.snake_colors[1],
+            game::Color::Green => self.snake_colors[2],
+            game::Color::Red => self.snake_colors[3],
+        }
+    }
+}
+
+const TRIANGLES: Arrows = Arrows {
+    up: "▲", right: "▶", down: "▼", left: "◀",
+    up_left: "◤", up_right: "◥", down_left: "◣", down_right: "◢",
+};
+
+pub static THEMES: [Theme; 5] = [
+    // the default one
+    Theme {
+        name: "unicode",
+        wide_glyphs: false,
+        border: "█",
+        border_periodic: "▒",
+        border_twist: "╳",
+        border_scroll: "░",
+        head: TRIANGLES,
+        body: "o",
+        tail: "∘",
+        food: "¤",
+        patrol: "⊕",
+        chaser: "Ж",
+        map_empty: "·",
+        snake_colors: [Tint::Ansi(7), Tint::Ansi(4), Tint::Ansi(2), Tint::Ansi(1)],
+        border_color: Tint::Default,
+        food_color: Tint::Ansi(3),
+        hazard_color: Tint::Ansi(5),
+    },
+    // for the terminals that know nothing but ASCII, colors included
+    Theme {
+        name: "ascii",
+        wide_glyphs: false,
+        border: "#",
+        border_periodic: "+",
+        border_twist: "x",
+        border_scroll: ":",
+        head: Arrows {
+            up: "^", right: ">", down: "v", left: "<",
+            up_left: "\\", up_right: "/", down_left: "/", down_right: "\\",
+        },
+        body: "o",
+        tail: ".",
+        food: "*",
+        patrol: "%",
+        chaser: "&",
+        map_empty: ".",
+        snake_colors: [Tint::Default; 4],
+        border_color: Tint::Default,
+        food_color: Tint::Default,
+        hazard_color: Tint::Default,
+    },
+    Theme {
+        name: "emoji",
+        wide_glyphs: true,
+        border: "🧱",
+        border_periodic: "🌀",
+        border_twist: "🔃",
+        border_scroll: "⬜",
+        head: Arrows {
+            up: "🐍", right: "🐍", down: "🐍", left: "🐍",
+            up_left: "🐍", up_right: "🐍", down_left: "🐍", down_right: "🐍",
+        },
+        body: "🟩",
+        tail: "🟢",
+        food: "🍎",
+        patrol: "🦂",
+        chaser: "👾",
+        map_empty: "⬛",
+        snake_colors: [Tint::Default; 4],
+        border_color: Tint::Default,
+        food_color: Tint::Default,
+        hazard_color: Tint::Default,
+    },
+    // the bright colors, solid symbols
+    Theme {
+        name: "contrast",
+        wide_glyphs: false,
+        border: "█",
+        border_periodic: "▓",
+        border_twist: "╳",
+        border_scroll: "▒",
+        head: TRIANGLES,
+        body: "█",
+        tail: "▓",
+        food: "◆",
+        patrol: "◉",
+        chaser: "Ж",
+        map_empty: "·",
+        snake_colors: [Tint::Ansi(15), Tint::Ansi(12), Tint::Ansi(10), Tint::Ansi(9)],
+        border_color: Tint::Ansi(15),
+        food_color: Tint::Ansi(11),
+        hazard_color: Tint::Ansi(13),
+    },
+    // the Okabe-Ito palette, told apart with any kind of color blindness
+    Theme {
+        name: "colorblind",
+        wide_glyphs: false,
+        border: "█",
+        border_periodic: "▒",
+        border_twist: "╳",
+        border_scroll: "░",
+        head: TRIANGLES,
+        body: "o",
+        tail: "∘",
+        food: "◆",
+        patrol: "⊕",
+        chaser: "Ж",
+        map_empty: "·",
+        snake_colors: [
+            Tint::Rgb(255, 255, 255),
+            Tint::Rgb(0, 114, 178),
+            Tint::Rgb(0, 158, 115),
+            Tint::Rgb(213, 94, 0),
+        ],
+        border_color: Tint::Default,
+        food_color: Tint::Rgb(240, 228, 66),
+        hazard_color: Tint::Rgb(204, 121, 167),
+    },
+];
+
+pub fn default() -> &'static Theme {
+    &THEMES[0]
+}
+
+pub fn by_name(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|t| t.name == name)
+}
+
+#[test]
+fn theme_simple_test() {
+    assert_eq!("unicode", default().name);
+    assert_eq!("▶", default().head(Dir::Right));
+    assert_eq!(Tint::Ansi(2), default().snake_color(game::Color::Green));
+
+    let ascii = by_name("ascii").unwrap();
+    assert_eq!("v", ascii.head(Dir::Down));
+    assert_eq!("/", ascii.head(Dir::UpRight));
+    assert!(by_name("nope").is_none());
+
+    assert_eq!("\x1b[38;2;1;2;3m", Tint::Rgb(1, 2, 3).to_string());
+}
+
+#[test]
+fn tint_fit_test() {
+    let orange = Tint::Rgb(230, 159, 0);
+    assert_eq!(orange, orange.fit(Depth::TrueColor));
+    assert_eq!(Tint::Ansi(178), orange.fit(Depth::Ansi256));
+    assert_eq!(Tint::Ansi(3), orange.fit(Depth::Ansi16));
+
+    // the palette colors are kept as long as the terminal has them
+    assert_eq!(Tint::Ansi(2), Tint::Ansi(2).fit(Depth::Ansi16));
+    assert_eq!(Tint::Ansi(8), Tint::Ansi(244).fit(Depth::Ansi16));
+    assert_eq!(Tint::Default, Tint::Default.fit(Depth::Ansi16));
+
+    // dark colors don't vanish in the background
+    assert_eq!(Tint::Ansi(2), Tint::Rgb(0, 87, 63).fit(Depth::Ansi16));
+}
+
+#[test]
+fn pattern_simple_test() {
+    let green = Tint::Rgb(0, 200, 100);
+    assert_eq!(green, Pattern::Solid.segment_color(green, 3, 4));
+
+    assert_eq!(green, Pattern::Gradient.segment_color(green, 0, 4));
+    assert_eq!(Tint::Rgb(0, 60, 30), Pattern::Gradient.segment_color(green, 3, 4));
+
+    let colors: Vec<_> = (0 .. 5).map(|i| Pattern::Stripes.segment_color(green, i, 5)).collect();
+    assert_eq!(vec![green, green, Tint::Rgb(0, 110, 55), Tint::Rgb(0, 110, 55), green], colors);
+
+    // nothing to shade in the default color
+    assert_eq!(Tint::Default, Pattern::Gradient.segment_color(Tint::Default, 3, 4));
+}
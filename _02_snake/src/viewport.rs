@@ -0,0 +1,153 @@
+// Viewport: the part of the board that fits on the screen
+
+use snake_engine::utypes::Pos;
+use snake_engine::utypes::Board;
+use snake_engine::game::Game;
+use snake_engine::topology::Edge;
+#[cfg(test)]
+use snake_engine::topology::Topology;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Viewport {
+    pub origin: Pos,    // board cell shown in the top-left corner
+    pub size: Board,
+}
+
+impl Viewport {
+    // the whole board at once
+    pub fn full(board: Board) -> Viewport {
+        Viewport { origin: Pos{x: 0, y: 0}, size: board }
+    }
+
+    // a view of `size` (cut to the board size) with `center` (a snake
+    // head) in the middle
+    pub fn around(game: &Game, center: Pos, size: Board) -> Viewport {
+        let board = game.board();
+        let mut view = Viewport::full(board);
+        view.size = Board{x: size.x.min(board.x), y: size.y.min(board.y)};
+
+        if view.size.x < board.x {
+            view.origin.x = center.x - view.size.x / 2;
+        }
+        if view.size.y < board.y {
+            view.origin.y = center.y - view.size.y / 2;
+        }
+
+        view.origin = view.normalize(game, view.origin);
+        view
+    }
+
+    // Scrolls just enough to keep `pos` at least a quarter of the view
+    // away from its edges. The view scrolls across the board edges that
+    // wrap around, and stops at the rest: past a twisted edge the board
+    // comes back mirrored, which no scrolling could show.
+    pub fn follow(&mut self, game: &Game, pos: Pos) {
+        let board = game.board();
+        let topology = game.topology();
+
+        let axis = |origin: i16, size: i16, n: i16, p: i16, edge: Edge| -> i16 {
+            if size >= n {
+                return 0;
+            }
+
+            let margin = size / 4;
+            let mut rel = p - origin;
+            if edge == Edge::Wrap {
+                rel = rel.rem_euclid(n);
+                // closer to come from before the view than after it
+                if rel >= size + (n - size) / 2 {
+                    rel -= n;
+                }
+            }
+
+            if rel < margin {
+                p - margin
+            } else if rel > size - 1 - margin {
+                p - (size - 1 - margin)
+            } else {
+                origin
+            }
+        };
+
+        let origin = Pos {
+            x: axis(self.origin.x, self.size.x, board.x, pos.x, topology.x),
+            y: axis(self.origin.y, self.size.y, board.y, pos.y, topology.y),
+        };
+        self.origin = self.normalize(game, origin);
+    }
+
+    // where a board cell shows up in the view, None if it's out of sight
+    pub fn to_view(self, game: &Game, pos: Pos) -> Option<Pos> {
+        let board = game.board();
+        let topology = game.topology();
+        let mut rel = pos - self.origin;
+
+        if topology.x == Edge::Wrap {
+            rel.x = rel.x.rem_euclid(board.x);
+        }
+        if topology.y == Edge::Wrap {
+            rel.y = rel.y.rem_euclid(board.y);
+        }
+
+        if rel.x < 0 || rel.y < 0 || rel.x >= self.size.x || rel.y >= self.size.y {
+            return None;
+        }
+        Some(rel)
+    }
+
+    fn normalize(&self, game: &Game, origin: Pos) -> Pos {
+        let board = game.board();
+        let topology = game.topology();
+        let axis = |o: i16, size: i16, n: i16, edge: Edge| {
+            if edge == Edge::Wrap { o.rem_euclid(n) } else { o.max(0).min(n - size) }
+        };
+
+        Pos {
+            x: axis(origin.x, self.size.x, board.x, topology.x),
+            y: axis(origin.y, self.size.y, board.y, topology.y),
+        }
+    }
+}
+
+#[test]
+fn viewport_walls_test() {
+    let game = Game::with_seed(Board{x: 40, y: 20}, 4, Topology::WALLS, 1);
+    let mut view = Viewport { origin: Pos{x: 0, y: 0}, size: Board{x: 20, y: 8} };
+
+    // inside the comfort zone nothing moves
+    view.follow(&game, Pos{x: 10, y: 4});
+    assert_eq!(Pos{x: 0, y: 0}, view.origin);
+
+    // close to the right edge of the view, scroll to keep the margin
+    view.follow(&game, Pos{x: 17, y: 4});
+    assert_eq!(Pos{x: 3, y: 0}, view.origin);
+    assert_eq!(Some(Pos{x: 14, y: 4}), view.to_view(&game, Pos{x: 17, y: 4}));
+    assert_eq!(None, view.to_view(&game, Pos{x: 2, y: 4}));
+
+    // but never past the walls
+    view.follow(&game, Pos{x: 39, y: 19});
+    assert_eq!(Pos{x: 20, y: 12}, view.origin);
+}
+
+#[test]
+fn viewport_periodic_test() {
+    let game = Game::with_seed(Board{x: 40, y: 20}, 4, Topology::TORUS, 1);
+    let mut view = Viewport { origin: Pos{x: 0, y: 0}, size: Board{x: 20, y: 8} };
+
+    // going off the left edge scrolls the view across it
+    view.follow(&game, Pos{x: 39, y: 4});
+    assert_eq!(Pos{x: 34, y: 0}, view.origin);
+    assert_eq!(Some(Pos{x: 5, y: 4}), view.to_view(&game, Pos{x: 39, y: 4}));
+    assert_eq!(Some(Pos{x: 6, y: 4}), view.to_view(&game, Pos{x: 0, y: 4}));
+
+    // the board fits whole in one direction, no scrolling that way
+    let view = Viewport::around(&game, game.snake().head(), Board{x: 20, y: 30});
+    assert_eq!(0, view.origin.y);
+    assert_eq!(Board{x: 20, y: 20}, view.size);
+
+    // only the edges that wrap around are scrolled across
+    let game = Game::with_seed(Board{x: 40, y: 20}, 4, Topology { x: Edge::Wrap, y: Edge::Twist }, 1);
+    let mut view = Viewport { origin: Pos{x: 0, y: 0}, size: Board{x: 20, y: 8} };
+    view.follow(&game, Pos{x: 39, y: 19});
+    assert_eq!(Pos{x: 34, y: 12}, view.origin);
+}